@@ -1,3 +1,42 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const WASM_PATH: &str = "../target/wasm32-wasip1/release/zellij_notify.wasm";
+
 fn main() {
-    println!("cargo:rerun-if-changed=../target/wasm32-wasip1/release/zellij_notify.wasm");
+    println!("cargo:rerun-if-changed={}", WASM_PATH);
+    println!("cargo:rerun-if-changed=../Cargo.toml");
+
+    // Only include the wasm when it exists, so a fresh checkout can still build the CLI
+    println!("cargo::rustc-check-cfg=cfg(embedded_wasm)");
+    if env::var_os("CARGO_FEATURE_EMBED_WASM").is_some() && Path::new(WASM_PATH).exists() {
+        println!("cargo:rustc-cfg=embedded_wasm");
+    }
+
+    // The embedded wasm is built from the plugin crate one level up, so its
+    // version is the one in that crate's manifest
+    let manifest = fs::read_to_string("../Cargo.toml").unwrap_or_default();
+    let version = package_version(&manifest).unwrap_or("unknown");
+    println!("cargo:rustc-env=ZNOTIFY_PLUGIN_VERSION={}", version);
+}
+
+// `version = "x.y.z"` from the [package] table
+fn package_version(manifest: &str) -> Option<&str> {
+    let mut in_package = false;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            continue;
+        }
+        if !in_package {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("version") {
+            let value = value.trim_start().strip_prefix('=')?.trim();
+            return value.strip_prefix('"')?.strip_suffix('"');
+        }
+    }
+    None
 }
@@ -0,0 +1,129 @@
+// The CLI's own settings file, ~/.config/znotify/config.toml (or under $XDG_CONFIG_HOME).
+// It extends the built-in notification table and sets defaults for notify flags.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_CONFIG: &str = r#"# znotify CLI settings
+
+# Show a desktop notification when a hook fires outside Zellij
+# (same as `znotify notify --desktop-fallback`)
+desktop_fallback = false
+
+# Notification names that also ring the terminal bell
+# (`znotify notify --bell` rings it for any name)
+bell = []
+
+# Append every notification to ~/.local/state/znotify/history.jsonl
+# (same as `znotify notify --history`; read it back with `znotify history`)
+history = false
+
+# Copy the bundled plugin into Zellij's plugin directory when it's missing,
+# before notifying from inside Zellij (same as `znotify notify --auto-install`)
+auto_install = false
+
+# Notification names for `znotify notify <name>`, merged over the built-in ones.
+# Keep these in sync with the presets in your Zellij plugin config.
+[presets]
+# build = "🔨"
+# stop = "🎉"
+"#;
+
+#[derive(Deserialize, Default)]
+pub struct CliConfig {
+    #[serde(default)]
+    pub desktop_fallback: bool,
+    #[serde(default)]
+    pub bell: Vec<String>,  // Notification names that ring the bell
+    #[serde(default)]
+    pub history: bool,
+    #[serde(default)]
+    pub auto_install: bool,
+    #[serde(default)]
+    pub presets: BTreeMap<String, String>,  // Notification name -> emoji
+}
+
+/// Read the config file, writing a commented default first when `create` is set and it's missing.
+pub fn load(path: &Path, create: bool) -> Result<CliConfig> {
+    if !path.exists() {
+        // A read-only home shouldn't break notifications, so creating the file is best effort
+        if create {
+            let _ = path.parent().map(fs::create_dir_all);
+            let _ = fs::write(path, DEFAULT_CONFIG);
+        }
+        return Ok(CliConfig::default());
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Top-level keys the config file actually sets, to tell configured values from defaults.
+pub fn file_keys(path: &Path) -> BTreeSet<String> {
+    fs::read_to_string(path).ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .map(|table| table.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// The built-in table with file presets applied: overrides keep their built-in slot,
+/// new names follow in alphabetical order.
+pub fn merge_presets(builtin: &[(&str, &str)], file: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    let mut merged: Vec<(String, String)> = builtin.iter()
+        .map(|(name, emoji)| {
+            let emoji = file.get(*name).map(|e| e.as_str()).unwrap_or(emoji);
+            (name.to_string(), emoji.to_string())
+        })
+        .collect();
+
+    for (name, emoji) in file {
+        if !builtin.iter().any(|(n, _)| n == name) {
+            merged.push((name.clone(), emoji.clone()));
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_creates_the_default_only_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("znotify/config.toml");
+
+        assert!(!load(&path, false).unwrap().history);
+        assert!(!path.exists());
+
+        load(&path, true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG);
+        assert!(file_keys(&path).contains("auto_install"));
+    }
+
+    #[test]
+    fn load_reports_a_broken_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "history = maybe").unwrap();
+        assert!(load(&path, false).is_err());
+    }
+
+    #[test]
+    fn file_presets_merge_over_the_builtin_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "[presets]\nstop = \"🎉\"\nbuild = \"🔨\"\nalpha = \"🅰️\"\n").unwrap();
+        let config = load(&path, false).unwrap();
+
+        let builtin = [("notification", "⚡"), ("stop", "✅")];
+        let merged = merge_presets(&builtin, &config.presets);
+        let merged: Vec<(&str, &str)> = merged.iter().map(|(n, e)| (n.as_str(), e.as_str())).collect();
+        assert_eq!(merged, [("notification", "⚡"), ("stop", "🎉"), ("alpha", "🅰️"), ("build", "🔨")]);
+    }
+}
@@ -0,0 +1,124 @@
+// The notification history, one JSON object per line in ~/.local/state/znotify/history.jsonl
+// (or under $XDG_STATE_HOME). `notify --history` appends to it, `znotify history` reads it back.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: u64,  // Unix time in seconds
+    pub name: String,
+    pub emoji: String,
+    pub session: Option<String>,
+    pub tab: Option<String>,  // Tab name, when Zellij exported one
+    pub pane_id: Option<String>,
+}
+
+/// Append one entry, creating the file and its directory as needed.
+pub fn append(path: &Path, entry: &Entry) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    // A single write of the whole line, so concurrent hooks can't interleave records
+    let mut file = OpenOptions::new().create(true).append(true).open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The last `count` entries (all of them when None), oldest first. Lines that aren't
+/// a valid entry are skipped; a missing file is an empty history.
+pub fn tail(path: &Path, count: Option<usize>) -> Result<Vec<Entry>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    let entries: Vec<Entry> = content.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = count.map(|n| entries.len().saturating_sub(n)).unwrap_or(0);
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+/// "2026-10-14 09:30:00" in UTC, without pulling in a date crate.
+pub fn format_utc(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let secs = timestamp % 86_400;
+
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year, month, day, secs / 3_600, secs % 3_600 / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: u64, name: &str) -> Entry {
+        Entry {
+            timestamp,
+            name: name.to_string(),
+            emoji: "✅".to_string(),
+            session: Some("main".to_string()),
+            tab: None,
+            pane_id: Some("4".to_string()),
+        }
+    }
+
+    #[test]
+    fn appends_one_json_object_per_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("znotify").join("history.jsonl");
+        append(&path, &entry(1_791_970_200, "stop")).unwrap();
+        append(&path, &entry(1_791_970_260, "notification")).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), concat!(
+            r#"{"timestamp":1791970200,"name":"stop","emoji":"✅","session":"main","tab":null,"pane_id":"4"}"#, "\n",
+            r#"{"timestamp":1791970260,"name":"notification","emoji":"✅","session":"main","tab":null,"pane_id":"4"}"#, "\n",
+        ));
+    }
+
+    #[test]
+    fn tail_reads_the_last_valid_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        assert!(tail(&path, None).unwrap().is_empty());
+
+        for (timestamp, name) in [(1, "stop"), (2, "notification"), (3, "posttooluse")] {
+            append(&path, &entry(timestamp, name)).unwrap();
+        }
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"timestamp\": \"torn wr\n").unwrap();
+
+        let names = |entries: Vec<Entry>| entries.into_iter().map(|e| e.name).collect::<Vec<_>>();
+        assert_eq!(names(tail(&path, Some(2)).unwrap()), ["notification", "posttooluse"]);
+        assert_eq!(names(tail(&path, None).unwrap()), ["stop", "notification", "posttooluse"]);
+        assert_eq!(names(tail(&path, Some(10)).unwrap()).len(), 3);
+    }
+
+    #[test]
+    fn formats_timestamps_as_utc() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00");
+        assert_eq!(format_utc(1_791_970_200), "2026-10-14 09:30:00");
+        assert_eq!(format_utc(951_782_400), "2000-02-29 00:00:00");
+    }
+}
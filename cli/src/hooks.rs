@@ -0,0 +1,165 @@
+// Splicing `znotify notify ...` commands into the settings files of tools with shell-command hooks.
+// Claude Code's matcher-group layout gets its own helper; anything else is addressed by a JSON
+// pointer into the parsed file, so a new tool only needs the right pointer.
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+/// The command a hook runs to send the given notification.
+pub fn hook_command(name: &str) -> String {
+    format!("znotify notify {}", name)
+}
+
+/// A hook command we installed; other tools' commands are never touched.
+pub fn is_znotify_command(command: &str) -> bool {
+    command.trim_start().starts_with("znotify notify ")
+}
+
+/// Every command in a list of Claude-style matcher groups.
+pub fn hook_commands(groups: &[Value]) -> impl Iterator<Item = &str> {
+    groups.iter()
+        .filter_map(|group| group.get("hooks").and_then(|h| h.as_array()))
+        .flatten()
+        .filter_map(|hook| hook.get("command").and_then(|c| c.as_str()))
+}
+
+/// Add `command` as a new matcher group under `settings.hooks.<event>`, creating both as needed.
+/// Returns false when the exact command is already installed for that event.
+pub fn install_command_hook(settings: &mut Value, event: &str, command: &str) -> Result<bool> {
+    let hooks = settings.as_object_mut()
+        .context("settings is not an object")?
+        .entry("hooks")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .context("hooks is not an object")?;
+
+    let groups = hooks.entry(event.to_string())
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .with_context(|| format!("hooks.{} is not an array", event))?;
+
+    if hook_commands(groups).any(|c| c == command) {
+        return Ok(false);
+    }
+    groups.push(json!({
+        "matcher": "",
+        "hooks": [{
+            "type": "command",
+            "command": command
+        }]
+    }));
+    Ok(true)
+}
+
+/// Add `command` to the list of commands at JSON pointer `pointer` (e.g. `/hooks/on_finish`),
+/// creating the list and any missing parent objects. Returns false when it's already there.
+pub fn splice_command(doc: &mut Value, pointer: &str, command: &str) -> Result<bool> {
+    if !pointer.starts_with('/') {
+        bail!("Pointer must start with '/', e.g. /hooks/on_finish");
+    }
+
+    let mut target = doc;
+    let mut path = String::new();
+    for token in pointer[1..].split('/') {
+        let key = token.replace("~1", "/").replace("~0", "~");
+        path.push('/');
+        path.push_str(token);
+        if target.is_null() {
+            *target = json!({});
+        }
+        target = match target {
+            Value::Object(map) => map.entry(key).or_insert(Value::Null),
+            Value::Array(items) => {
+                let len = items.len();
+                key.parse::<usize>().ok()
+                    .and_then(|i| items.get_mut(i))
+                    .with_context(|| format!("{} is out of range (array has {} items)", path, len))?
+            }
+            _ => bail!("{} is inside a value that is not an object or array", path),
+        };
+    }
+
+    match target {
+        Value::Null => {
+            *target = json!([command]);
+            Ok(true)
+        }
+        Value::Array(commands) => {
+            if commands.iter().any(|c| c.as_str() == Some(command)) {
+                return Ok(false);
+            }
+            commands.push(json!(command));
+            Ok(true)
+        }
+        Value::String(existing) if existing == command => Ok(false),
+        _ => bail!("{} already holds a value that is not a list of commands", pointer),
+    }
+}
+
+/// Parse a settings file; an empty file is an empty object.
+pub fn parse(text: &str, format: Format) -> Result<Value> {
+    if text.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    match format {
+        Format::Json => serde_json::from_str(text).context("Failed to parse settings JSON"),
+        Format::Toml => {
+            let table: toml::Table = toml::from_str(text).context("Failed to parse settings TOML")?;
+            serde_json::to_value(table).context("Failed to convert settings TOML")
+        }
+    }
+}
+
+pub fn render(doc: &Value, format: Format) -> Result<String> {
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(doc)?),
+        Format::Toml => toml::to_string_pretty(doc).context("Settings can't be written back as TOML"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splices_into_a_generic_object() {
+        let mut doc = json!({"theme": "dark", "hooks": {"on_start": ["echo hi"]}});
+        assert!(splice_command(&mut doc, "/hooks/on_finish", "znotify notify stop").unwrap());
+        assert!(splice_command(&mut doc, "/hooks/on_start", "znotify notify stop").unwrap());
+        assert!(!splice_command(&mut doc, "/hooks/on_start", "znotify notify stop").unwrap());
+        assert_eq!(doc, json!({
+            "theme": "dark",
+            "hooks": {"on_start": ["echo hi", "znotify notify stop"], "on_finish": ["znotify notify stop"]},
+        }));
+    }
+
+    #[test]
+    fn creates_missing_parents_and_unescapes_pointer_tokens() {
+        let mut doc = json!({});
+        assert!(splice_command(&mut doc, "/events/a~1b/run", "znotify notify stop").unwrap());
+        assert_eq!(doc, json!({"events": {"a/b": {"run": ["znotify notify stop"]}}}));
+    }
+
+    #[test]
+    fn refuses_to_overwrite_other_values() {
+        let mut doc = json!({"hooks": {"on_finish": "say done", "list": [1]}});
+        assert!(splice_command(&mut doc, "/hooks/on_finish", "znotify notify stop").is_err());
+        assert!(splice_command(&mut doc, "/hooks/list/3", "znotify notify stop").is_err());
+        assert!(splice_command(&mut doc, "hooks", "znotify notify stop").is_err());
+    }
+
+    #[test]
+    fn toml_settings_round_trip() {
+        let mut doc = parse("[hooks]\non_finish = [\"say done\"]\n", Format::Toml).unwrap();
+        splice_command(&mut doc, "/hooks/on_finish", "znotify notify stop").unwrap();
+        assert_eq!(render(&doc, Format::Toml).unwrap(), "[hooks]\non_finish = [\n    \"say done\",\n    \"znotify notify stop\",\n]\n");
+        assert_eq!(parse("  \n", Format::Json).unwrap(), json!({}));
+    }
+}
@@ -0,0 +1,377 @@
+// Minimal, text-preserving edits to the znotify plugin block in Zellij's config.kdl.
+// We only ever touch the `presets r#"..."#` string inside our own block, so the
+// rest of the user's config (comments, formatting, other plugins) stays as written.
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::ops::Range;
+
+const PLUGIN_FILE: &str = "zellij-notify.wasm";
+const PRESETS_KEY: &str = "presets ";
+const LOAD_PLUGINS: &str = "load_plugins";
+
+/// Byte range of the znotify plugin block, from the start of its first line to the closing brace.
+/// Prefers a block that already holds presets, since the wasm path can also appear in keybinds.
+pub fn find_plugin_block(config: &str) -> Option<Range<usize>> {
+    let mut first = None;
+
+    for (idx, _) in config.match_indices(PLUGIN_FILE) {
+        let line_start = config[..idx].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = config[idx..].find('\n').map(|i| idx + i).unwrap_or(config.len());
+
+        // Skip commented-out references
+        if config[line_start..idx].trim_start().starts_with("//") {
+            continue;
+        }
+
+        let Some(open) = config[idx..line_end].find('{').map(|i| idx + i) else {
+            continue;
+        };
+        let Some(close) = matching_brace(config, open) else {
+            continue;
+        };
+
+        let block = line_start..close + 1;
+        if presets_string(&config[block.clone()]).is_some() {
+            return Some(block);
+        }
+        first.get_or_insert(block);
+    }
+
+    first
+}
+
+/// The presets JSON inside a plugin block, with its byte range in `config`.
+pub fn find_presets(config: &str, block: &Range<usize>) -> Option<(Range<usize>, String)> {
+    let (_, json) = presets_string(&config[block.clone()])?;
+    let range = block.start + json.start..block.start + json.end;
+    let json = config[range.clone()].to_string();
+    Some((range, json))
+}
+
+// Byte ranges of the `presets r#"..."#` raw string in `text`, quotes and hashes included, and
+// of the JSON inside it. Like matching_brace, the string only ends at a quote followed by as
+// many hashes as it opened with, so a `"#️⃣"` emoji inside an `r##"..."##` is kept whole.
+fn presets_string(text: &str) -> Option<(Range<usize>, Range<usize>)> {
+    for (idx, _) in text.match_indices(PRESETS_KEY) {
+        let start = idx + PRESETS_KEY.len();
+        if text.as_bytes().get(start) != Some(&b'r') {
+            continue;
+        }
+        let hashes = text[start + 1..].bytes().take_while(|b| *b == b'#').count();
+        if text.as_bytes().get(start + 1 + hashes) != Some(&b'"') {
+            continue;
+        }
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let body = start + 2 + hashes;
+        let end = body + text[body..].find(&terminator)?;
+        return Some((start..end + terminator.len(), body..end));
+    }
+    None
+}
+
+// `json` as a KDL raw string, with enough hashes that nothing inside it closes the string
+fn raw_string(json: &str) -> String {
+    let mut hashes = "#".to_string();
+    while json.contains(&format!("\"{}", hashes)) {
+        hashes.push('#');
+    }
+    format!("r{}\"{}\"{}", hashes, json, hashes)
+}
+
+/// The presets configured in our plugin block, in file order.
+pub fn read_presets(config: &str) -> Result<Option<Map<String, Value>>> {
+    let Some(block) = find_plugin_block(config) else {
+        return Ok(None);
+    };
+    let Some((_, json)) = find_presets(config, &block) else {
+        return Ok(Some(Map::new()));
+    };
+    let presets = serde_json::from_str(&json)
+        .context("Failed to parse presets JSON in Zellij config")?;
+    Ok(Some(presets))
+}
+
+/// Rewrite the presets string of our plugin block, adding one if the block has none.
+/// Returns None when the config has no znotify plugin block.
+pub fn write_presets(config: &str, presets: &Map<String, Value>) -> Option<String> {
+    let block = find_plugin_block(config)?;
+
+    // The whole raw string is replaced, so its hashes can change with the JSON
+    if let Some((literal, _)) = presets_string(&config[block.clone()]) {
+        let range = block.start + literal.start..block.start + literal.end;
+        let indent = line_indent(config, range.start);
+        let json = raw_string(&format_presets(presets, &indent));
+        return Some(format!("{}{}{}", &config[..range.start], json, &config[range.end..]));
+    }
+
+    // No presets yet: insert a line right before the closing brace
+    let indent = format!("{}    ", line_indent(config, block.end - 1));
+    let line = format!("{}{}", PRESETS_KEY, raw_string(&format_presets(presets, &indent)));
+    Some(insert_line(config, &block, &line))
+}
+
+/// The value of a `key "value"` setting in our plugin block.
+pub fn read_option(config: &str, key: &str) -> Option<String> {
+    let block = find_plugin_block(config)?;
+    let (range, _) = find_option(config, &block, key)?;
+    let line = &config[range];
+    let start = line.find('"')? + 1;
+    let end = line.rfind('"').filter(|end| *end >= start)?;
+    Some(line[start..end].replace("\\\"", "\"").replace("\\\\", "\\"))
+}
+
+/// Set a `key "value"` setting in our plugin block, replacing the existing line or adding one.
+/// Returns None when the config has no znotify plugin block.
+pub fn write_option(config: &str, key: &str, value: &str) -> Option<String> {
+    let block = find_plugin_block(config)?;
+    let line = format!("{} \"{}\"", key, value.replace('\\', "\\\\").replace('"', "\\\""));
+
+    if let Some((range, indent)) = find_option(config, &block, key) {
+        return Some(format!("{}{}{}{}", &config[..range.start], indent, line, &config[range.end..]));
+    }
+    Some(insert_line(config, &block, &line))
+}
+
+// The byte range and indent of the line setting `key` directly inside the block
+fn find_option(config: &str, block: &Range<usize>, key: &str) -> Option<(Range<usize>, String)> {
+    let mut offset = block.start;
+    for line in config[block.clone()].split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let body = line.trim_start();
+        let is_setting = body.strip_prefix(key)
+            .is_some_and(|rest| rest.starts_with(' ') || rest.starts_with('\t'));
+        if is_setting {
+            let indent = line[..line.len() - body.len()].to_string();
+            return Some((start..start + line.trim_end().len(), indent));
+        }
+    }
+    None
+}
+
+// Add a line to the end of the block, indented one level deeper than its closing brace
+fn insert_line(config: &str, block: &Range<usize>, line: &str) -> String {
+    let close = block.end - 1;
+    let indent = format!("{}    ", line_indent(config, close));
+    let line_start = config[..close].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    // A brace sharing its line with other content gets the new line put before it
+    let own_line = config[line_start..close].trim().is_empty();
+    let insert_at = if own_line { line_start } else { close };
+    let line = format!("{}{}\n", indent, line);
+    let line = if own_line { line } else { format!("\n{}", line) };
+    format!("{}{}{}", &config[..insert_at], line, &config[insert_at..])
+}
+
+/// Add a plugin entry to the top-level `load_plugins` block, or append a new block holding it.
+pub fn add_to_load_plugins(config: &str, entry: &str) -> String {
+    let entry = entry.trim_end();
+
+    if let Some(block) = find_load_plugins(config) {
+        let indent = format!("{}    ", line_indent(config, block.end - 1));
+        let text = entry.lines().collect::<Vec<_>>().join(&format!("\n{}", indent));
+        return insert_line(config, &block, &text);
+    }
+
+    let body: Vec<String> = entry.lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("    {}", line) })
+        .collect();
+    let mut updated = config.to_string();
+    if !updated.is_empty() {
+        if !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+        updated.push('\n');
+    }
+    updated.push_str(&format!("{} {{\n{}\n}}\n", LOAD_PLUGINS, body.join("\n")));
+    updated
+}
+
+// Byte range of the first uncommented `load_plugins { ... }` block
+fn find_load_plugins(config: &str) -> Option<Range<usize>> {
+    let mut offset = 0;
+    for line in config.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let Some(rest) = line.trim_start().strip_prefix(LOAD_PLUGINS) else {
+            continue;
+        };
+        if !rest.trim_start().starts_with('{') {
+            continue;
+        }
+        let open = start + line.find('{')?;
+        return matching_brace(config, open).map(|close| start..close + 1);
+    }
+    None
+}
+
+/// Remove our plugin block entirely, including its trailing newline.
+/// Returns None when the config has no znotify plugin block.
+pub fn remove_plugin_block(config: &str) -> Option<String> {
+    let block = find_plugin_block(config)?;
+    let end = if config[block.end..].starts_with('\n') { block.end + 1 } else { block.end };
+    Some(format!("{}{}", &config[..block.start], &config[end..]))
+}
+
+// One preset per line, the layout `znotify config` prints
+fn format_presets(presets: &Map<String, Value>, indent: &str) -> String {
+    if presets.is_empty() {
+        return "{}".to_string();
+    }
+
+    let lines: Vec<String> = presets.iter()
+        .map(|(name, preset)| format!("{}    {}: {}", indent, Value::String(name.clone()), compact(preset)))
+        .collect();
+    format!("{{\n{}\n{}}}", lines.join(",\n"), indent)
+}
+
+// {"emoji":"✅"} -> {"emoji": "✅"}
+fn compact(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let fields: Vec<String> = map.iter()
+                .map(|(k, v)| format!("{}: {}", Value::String(k.clone()), compact(v)))
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
+fn line_indent(config: &str, pos: usize) -> String {
+    let line_start = config[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    config[line_start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+}
+
+// Index of the brace closing the one at `open`, skipping strings and comments
+fn matching_brace(text: &str, open: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0usize;
+    let mut i = open;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = text[i..].find('\n').map(|n| i + n).unwrap_or(bytes.len());
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = text[i + 2..].find("*/").map(|n| i + 2 + n + 2).unwrap_or(bytes.len());
+                continue;
+            }
+            b'r' if matches!(bytes.get(i + 1), Some(b'#') | Some(b'"')) => {
+                // Raw string: r#"..."# with any number of hashes
+                let hashes = text[i + 1..].bytes().take_while(|b| *b == b'#').count();
+                if bytes.get(i + 1 + hashes) == Some(&b'"') {
+                    let terminator = format!("\"{}", "#".repeat(hashes));
+                    let body = i + 2 + hashes;
+                    i = text[body..].find(&terminator).map(|n| body + n + terminator.len()).unwrap_or(bytes.len());
+                    continue;
+                }
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SAMPLE: &str = r##"// My Zellij config
+keybinds {
+    shared {
+        // Run the plugin from a key: "file:~/.config/zellij/plugins/zellij-notify.wasm"
+        bind "Alt n" { LaunchOrFocusPlugin "file:~/.config/zellij/plugins/zellij-notify.wasm" { floating true; } }
+    }
+}
+
+plugins {
+    zellij-notify location="file:~/.config/zellij/plugins/zellij-notify.wasm" {
+        separator " | "
+        presets r#"{
+            "stop": {"emoji": "✅", "label": "done"},
+            "deploy": {"emoji": "🚀"}
+        }"#
+    }
+}
+
+theme "dracula"
+"##;
+
+    fn presets(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn reads_presets_in_file_order() {
+        let presets = read_presets(SAMPLE).unwrap().unwrap();
+        assert_eq!(presets.keys().collect::<Vec<_>>(), ["stop", "deploy"]);
+        assert_eq!(presets["stop"], json!({"emoji": "✅", "label": "done"}));
+    }
+
+    #[test]
+    fn writes_presets_leaving_the_rest_alone() {
+        let mut edited = read_presets(SAMPLE).unwrap().unwrap();
+        edited.shift_remove("deploy");
+        edited.insert("build".to_string(), json!({"emoji": "🔨"}));
+
+        let updated = write_presets(SAMPLE, &edited).unwrap();
+        assert_eq!(read_presets(&updated).unwrap().unwrap(), edited);
+        assert!(updated.contains("        presets r#\"{\n            \"stop\": {\"emoji\": \"✅\", \"label\": \"done\"},\n            \"build\": {\"emoji\": \"🔨\"}\n        }\"#"));
+        assert!(updated.starts_with("// My Zellij config\nkeybinds {"));
+        assert!(updated.ends_with("        }\"#\n    }\n}\n\ntheme \"dracula\"\n"));
+        assert_eq!(read_option(&updated, "separator").as_deref(), Some(" | "));
+    }
+
+    #[test]
+    fn adds_a_presets_line_to_a_block_without_one() {
+        let config = "plugins {\n    notify location=\"file:/p/zellij-notify.wasm\" {\n        separator \"-\"\n    }\n}\n";
+        let updated = write_presets(config, &presets(json!({"stop": {"emoji": "✅"}}))).unwrap();
+        assert_eq!(updated, "plugins {\n    notify location=\"file:/p/zellij-notify.wasm\" {\n        separator \"-\"\n        presets r#\"{\n            \"stop\": {\"emoji\": \"✅\"}\n        }\"#\n    }\n}\n");
+    }
+
+    #[test]
+    fn a_hash_leading_emoji_is_kept_inside_the_presets_string() {
+        let config = "plugins {\n    notify location=\"file:/p/zellij-notify.wasm\" {\n        presets r#\"{}\"#\n    }\n}\n";
+        let keycap = presets(json!({"keycap": {"emoji": "#️⃣"}, "stop": {"emoji": "✅"}}));
+
+        // The JSON holds a `"#`, so the string needs a second hash to stay open past it
+        let updated = write_presets(config, &keycap).unwrap();
+        assert!(updated.contains("        presets r##\"{\n            \"keycap\": {\"emoji\": \"#️⃣\"},\n"));
+        assert!(updated.ends_with("        }\"##\n    }\n}\n"));
+        assert_eq!(read_presets(&updated).unwrap().unwrap(), keycap);
+
+        // Rewritten without it, one hash is enough again
+        let updated = write_presets(&updated, &presets(json!({"stop": {"emoji": "✅"}}))).unwrap();
+        assert!(updated.contains("presets r#\"{\n"));
+        assert_eq!(read_presets(&updated).unwrap().unwrap()["stop"], json!({"emoji": "✅"}));
+    }
+
+    #[test]
+    fn no_plugin_block_means_nothing_to_edit() {
+        let config = "theme \"dracula\"\n";
+        assert!(read_presets(config).unwrap().is_none());
+        assert!(write_presets(config, &Map::new()).is_none());
+    }
+}
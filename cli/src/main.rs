@@ -1,31 +1,112 @@
-use anyhow::{Context, Result, bail};
-use clap::{Parser, Subcommand};
-use serde_json::{json, Value};
+mod cli_config;
+mod history;
+mod hooks;
+mod kdl_config;
+
+use anyhow::{anyhow, Context, Result, bail};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use cli_config::CliConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use zellij_notify_preset::{self as preset, PresetConfig, Problem};
+
+const ZELLIJ_NOT_FOUND: &str = "zellij not found on PATH; install Zellij or ensure it's in your shell's PATH";
+
+// `notify --attempts` and `--retry-delay-ms` defaults
+const DEFAULT_ATTEMPTS: u32 = 3;
+const DEFAULT_RETRY_DELAY_MS: u64 = 100;
+
+// How long `list` waits for the plugin to answer a query
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+// How often `logs --follow` checks the file for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+// Emojis `znotify test` decorates with, first one not used by a preset; none is in NOTIFY_CONFIG
+const TEST_EMOJIS: &[&str] = &["🧪", "🔬", "🛸"];
+
+// How long `znotify test` waits for the plugin to apply each step
+const TEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Set by --quiet; silences `info!` output, leaving errors, warnings and requested output
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Informational messages (✅ lines and the like), dropped under --quiet
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
+
+// Set by `notify --verbose`; traces how the CLI talks to Zellij
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+// Diagnostic lines for --verbose, on stderr so they never mix with requested output
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if VERBOSE.load(Ordering::Relaxed) {
+            eprintln!("[znotify] {}", format!($($arg)*));
+        }
+    };
+}
 
-// Embed WASM binary at compile time
+// Embed WASM binary at compile time; empty when the build didn't bundle it (see build.rs)
+#[cfg(embedded_wasm)]
 const WASM_BYTES: &[u8] = include_bytes!("../../target/wasm32-wasip1/release/zellij_notify.wasm");
+#[cfg(not(embedded_wasm))]
+const WASM_BYTES: &[u8] = &[];
+
+// Version of the plugin crate the embedded wasm was built from (see build.rs)
+const PLUGIN_VERSION: &str = env!("ZNOTIFY_PLUGIN_VERSION");
 
-// Notification presets (name -> emoji)
+// Notification presets (name -> emoji); ~/.config/znotify/config.toml can extend them
 const NOTIFY_CONFIG: &[(&str, &str)] = &[
     ("notification", "⚡"),
     ("posttooluse", "⚡"),
     ("stop", "✅"),
     ("subagent-stop", "🔴"),
+    ("pretooluse", "🔧"),
+    ("userpromptsubmit", "💬"),
+    ("sessionstart", "🚀"),
+    ("sessionend", "🏁"),
+];
+
+// Emoji for `notify --exit` with status 0 / anything else; the plugin's command-pane pair
+const EXIT_SUCCESS_EMOJI: &str = "✅";
+const EXIT_FAILURE_EMOJI: &str = "❌";
+
+// Pipe args naming the target tab outright; with one of them the message goes out without
+// this pane's id and tab name, which the plugin would otherwise resolve first
+const EXPLICIT_TARGET_ARGS: &[&str] = &["tab_position", "tab_number", "tab_name"];
+
+// Claude Code hook events (event -> notification name, installed by default)
+const CLAUDE_HOOKS: &[(&str, &str, bool)] = &[
+    ("Notification", "notification", true),
+    ("Stop", "stop", true),
+    ("PostToolUse", "posttooluse", true),
+    ("SubagentStop", "subagent-stop", true),
+    ("PreToolUse", "pretooluse", false),
+    ("UserPromptSubmit", "userpromptsubmit", false),
+    ("SessionStart", "sessionstart", false),
+    ("SessionEnd", "sessionend", false),
 ];
 
+// Presets are filled in from the notification table by `config_template`
 const ZELLIJ_CONFIG_TEMPLATE: &str = r##"plugin location="file:~/.config/zellij/plugins/zellij-notify.wasm" {
     debug "false"
-    presets r#"{
-        "notification": {"emoji": "⚡"},
-        "posttooluse": {"emoji": "⚡"},
-        "stop": {"emoji": "✅"},
-        "subagent-stop": {"emoji": "🔴"}
-    }"#
+    presets r#"{}"#
 }
 "##;
 
@@ -33,296 +114,3106 @@ const ZELLIJ_CONFIG_TEMPLATE: &str = r##"plugin location="file:~/.config/zellij/
 #[command(name = "znotify")]
 #[command(about = "Zellij notification plugin CLI", long_about = None)]
 struct Cli {
+    /// Print what would be written or deleted without touching any files
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Only print errors and warnings, not success messages
+    #[arg(long, short, global = true)]
+    quiet: bool,
+
+    /// Pipe name the plugin listens on; must match its `pipe_name` config
+    #[arg(long, global = true, env = "ZNOTIFY_PIPE_NAME", default_value = "notify")]
+    pipe_name: String,
+
+    /// Zellij config directory (default: $ZELLIJ_CONFIG_DIR, else $XDG_CONFIG_HOME/zellij or ~/.config/zellij)
+    #[arg(long, global = true, value_name = "DIR")]
+    config_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Set everything up: install the plugin, add its config block and the Claude hooks
+    Init,
     /// Claude Code integration commands
     Claude {
         #[command(subcommand)]
         command: ClaudeCommands,
     },
+    /// Hook commands for tools other than Claude Code
+    Hooks {
+        #[command(subcommand)]
+        command: HooksCommands,
+    },
     /// Send notification to Zellij
-    Notify {
-        /// Notification name (notification, stop, posttooluse, subagent-stop)
-        name: String,
+    Notify(NotifyArgs),
+    /// Clear the notification from the current tab
+    Clear {
+        /// Only strip this emoji, leaving a tab that shows another one alone
+        #[arg(long)]
+        emoji: Option<String>,
+    },
+    /// Switch to a tab and clear its notification
+    Goto {
+        /// Tab position (0-indexed, as `list` prints it) or tab name
+        tab: String,
+    },
+    /// List tabs that currently show a notification
+    List {
+        /// Print the plugin's JSON reply as-is
+        #[arg(long)]
+        json: bool,
     },
     /// Install plugin to Zellij
-    InstallPlugin,
+    InstallPlugin {
+        /// Write the plugin here instead of <config dir>/plugins/zellij-notify.wasm
+        #[arg(long, value_name = "FILE")]
+        path: Option<PathBuf>,
+    },
+    /// Remove the installed plugin
+    UninstallPlugin {
+        /// Also offer to remove the plugin block from config.kdl
+        #[arg(long)]
+        purge: bool,
+    },
     /// Show installation status
-    Status,
+    Status {
+        /// Print a machine-readable JSON object instead
+        #[arg(long)]
+        json: bool,
+
+        /// Check for the plugin at this path instead of the default location
+        #[arg(long, value_name = "FILE")]
+        path: Option<PathBuf>,
+    },
+    /// Show the CLI version and the version of the embedded plugin
+    Version {
+        /// Print a machine-readable JSON object instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Diagnose common setup problems
+    Doctor,
+    /// Decorate this tab with a test emoji and clear it again, to check the whole setup
+    Test,
+    /// Print notifications recorded with `notify --history`, oldest first
+    History {
+        /// Only show the last N entries
+        #[arg(long, value_name = "N")]
+        tail: Option<usize>,
+
+        /// Print the entries as JSON lines instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the plugin's log_file
+    Logs {
+        /// Keep printing new lines as the plugin writes them
+        #[arg(long, short)]
+        follow: bool,
+
+        /// Only show the last N lines
+        #[arg(long, short = 'n', value_name = "N")]
+        lines: Option<usize>,
+
+        /// Log file to read (default: log_file from the plugin block in config.kdl)
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+    },
     /// Print Zellij config template
-    Config,
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
+    },
+    /// Add the plugin block to config.kdl unless it already has one
+    InstallConfig,
+    /// Never decorate a tab, by position or name (`*` matches anything)
+    Mute {
+        /// Tab position or name, e.g. 3, logs or "*logs*"
+        tab: String,
+    },
+    /// Manage emoji presets in the Zellij config
+    Preset {
+        #[command(subcommand)]
+        command: PresetCommands,
+    },
+    /// Check a presets JSON object the way the plugin loads it, before pasting it into config.kdl
+    ValidatePresets {
+        /// Presets JSON, or - to read it from stdin
+        #[arg(value_name = "JSON|-")]
+        json: String,
+    },
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions {
+        shell: Shell,
+    },
+}
+
+#[derive(Args)]
+struct NotifyArgs {
+    /// Notification name (notification, stop, posttooluse, subagent-stop, pretooluse, ...)
+    #[arg(required_unless_present = "list")]
+    name: Option<String>,
+    /// Print the available notification names and their emojis, one `name<TAB>emoji` per line
+    #[arg(long, conflicts_with = "name")]
+    list: bool,
+    /// With --list, print a JSON array of {"name", "emoji"} objects instead
+    #[arg(long, conflicts_with = "name")]
+    json: bool,
+    /// Show this emoji instead of the preset's; any name is accepted when set
+    #[arg(long)]
+    emoji: Option<String>,
+    /// Exit status of the command being reported: ✅ for 0, ❌ for anything else,
+    /// instead of the preset's emoji; any name is accepted when set
+    #[arg(long, value_name = "CODE", allow_negative_numbers = true, conflicts_with = "emoji")]
+    exit: Option<i32>,
+    /// Short text shown after the emoji, e.g. an exit code
+    #[arg(long)]
+    message: Option<String>,
+    /// Claude agent or subagent sending this; its emoji from the plugin's agent_emojis
+    /// replaces the preset's when it has one
+    #[arg(long, value_name = "ID")]
+    agent: Option<String>,
+    /// Rename the tab to this in the same step, e.g. to the task it now runs
+    #[arg(long, value_name = "TEXT", conflicts_with_all = ["all", "tabs"])]
+    set_name: Option<String>,
+    /// Mark every tab in the session instead of just this one
+    #[arg(long)]
+    all: bool,
+    /// With --all, leave the focused tab alone
+    #[arg(long, requires = "all")]
+    skip_active: bool,
+    /// Mark these tab positions instead of this tab, e.g. 1,3,5
+    #[arg(long, value_delimiter = ',', value_name = "POSITIONS", conflicts_with = "all")]
+    tabs: Vec<usize>,
+    /// Mark the tab the tab bar shows as number N (counting from 1) instead of this tab
+    #[arg(long, value_name = "N", conflicts_with_all = ["all", "tabs"],
+          value_parser = clap::value_parser!(u32).range(1..))]
+    tab: Option<u32>,
+    /// Notify this Zellij session instead of the current one; marks its active tab
+    #[arg(long, value_name = "NAME")]
+    session: Option<String>,
+    /// Show a desktop notification instead when not running inside Zellij
+    /// (also enabled by ZNOTIFY_DESKTOP_FALLBACK=1)
+    #[arg(long)]
+    desktop_fallback: bool,
+    /// Ring the terminal bell once the notification is sent (also enabled by ZNOTIFY_BELL=1)
+    #[arg(long)]
+    bell: bool,
+    /// Append the notification to the history file read by `znotify history`
+    #[arg(long)]
+    history: bool,
+    /// Install the bundled plugin first if it's missing (only inside Zellij)
+    #[arg(long)]
+    auto_install: bool,
+    /// Print what was sent where to stdout, e.g. for a hook's output in a transcript
+    #[arg(long)]
+    echo: bool,
+    /// Times to try `zellij pipe` before giving up, for Zellij being briefly unavailable
+    #[arg(long, env = "ZNOTIFY_ATTEMPTS", default_value_t = DEFAULT_ATTEMPTS, value_name = "N",
+          value_parser = clap::value_parser!(u32).range(1..))]
+    attempts: u32,
+    /// Print the zellij pipe command, what it was resolved from and its output to stderr
+    #[arg(long, short)]
+    verbose: bool,
+    /// Wait before the first retry, doubled after each one
+    #[arg(long, env = "ZNOTIFY_RETRY_DELAY_MS", default_value_t = DEFAULT_RETRY_DELAY_MS, value_name = "MS")]
+    retry_delay_ms: u64,
 }
 
 #[derive(Subcommand)]
 enum ClaudeCommands {
     /// Install Claude Code hooks
-    InstallHooks,
+    InstallHooks {
+        /// Don't back up settings.json before modifying it
+        #[arg(long)]
+        no_backup: bool,
+        /// Comma-separated hook events to install (default: Notification,Stop,PostToolUse,SubagentStop).
+        /// Also available: PreToolUse, UserPromptSubmit, SessionStart, SessionEnd
+        #[arg(long, value_delimiter = ',')]
+        events: Vec<String>,
+        /// Replace a settings.json that isn't valid JSON, after backing it up
+        #[arg(long)]
+        force: bool,
+    },
     /// Uninstall Claude Code hooks
-    UninstallHooks,
+    UninstallHooks {
+        /// Don't back up settings.json before modifying it
+        #[arg(long)]
+        no_backup: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HooksCommands {
+    /// Print the shell command a hook should run for a notification or Claude event
+    Print {
+        /// Notification name or Claude hook event (stop, Notification, ...)
+        event: String,
+    },
+    /// Add the hook command to another tool's settings file
+    Install {
+        /// Notification name or Claude hook event (stop, Notification, ...)
+        event: String,
+        /// Settings file to modify; created if missing
+        #[arg(long, value_name = "PATH")]
+        config: PathBuf,
+        /// JSON pointer to the list of commands, e.g. /hooks/on_finish
+        #[arg(long)]
+        pointer: String,
+        /// File format (default: from the file extension, else json)
+        #[arg(long, value_enum)]
+        format: Option<hooks::Format>,
+        /// Don't back up the file before modifying it
+        #[arg(long)]
+        no_backup: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the effective settings and where each one comes from
+    Show {
+        /// Print a machine-readable JSON object instead
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PresetCommands {
+    /// List presets configured in config.kdl
+    List,
+    /// Add or update a preset
+    Add {
+        /// Preset name, as passed to `znotify notify`
+        name: String,
+        /// Emoji to show on the tab
+        emoji: String,
+    },
+    /// Remove a preset
+    Remove {
+        /// Preset name
+        name: String,
+    },
+}
+
+// Only the setup commands write the commented default config.toml, and only they treat a
+// broken one as fatal; everything else, hook-fired `notify` above all, warns and runs on defaults
+fn load_cli_config(command: &Commands, dry_run: bool) -> Result<CliConfig> {
+    let setup = matches!(command, Commands::Init | Commands::Config { .. });
+    match get_cli_config_path().and_then(|path| cli_config::load(&path, setup && !dry_run)) {
+        Ok(config) => Ok(config),
+        Err(e) if !setup => {
+            eprintln!("⚠️  Using default settings: {:#}", e);
+            Ok(CliConfig::default())
+        }
+        Err(e) => Err(e),
+    }
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    QUIET.store(cli.quiet, Ordering::Relaxed);
+
+    let dry_run = cli.dry_run;
+    let pipe_name = cli.pipe_name.as_str();
+    let config_dir = cli.config_dir.as_deref();
+    let cli_config = load_cli_config(&cli.command, dry_run)?;
 
     match cli.command {
+        Commands::Init => init(config_dir, &cli_config, dry_run),
         Commands::Claude { command } => match command {
-            ClaudeCommands::InstallHooks => claude_install_hooks(),
-            ClaudeCommands::UninstallHooks => claude_uninstall_hooks(),
+            ClaudeCommands::InstallHooks { no_backup, events, force } => {
+                claude_install_hooks(&events, !no_backup, force, dry_run)?;
+                sync_presets(config_dir, &cli_config, dry_run)
+            }
+            ClaudeCommands::UninstallHooks { no_backup } => claude_uninstall_hooks(!no_backup, dry_run),
+        },
+        Commands::Hooks { command } => match command {
+            HooksCommands::Print { event } => hooks_print(&event, &cli_config),
+            HooksCommands::Install { event, config, pointer, format, no_backup } =>
+                hooks_install(&event, &cli_config, &config, &pointer, format, !no_backup, dry_run),
+        },
+        Commands::Notify(args) => notify(&args, config_dir, pipe_name, &cli_config),
+        Commands::Clear { emoji } => clear(pipe_name, emoji.as_deref()),
+        Commands::Goto { tab } => goto(pipe_name, &tab),
+        Commands::List { json } => list(pipe_name, json),
+        Commands::InstallPlugin { path } => install_plugin(config_dir, path.as_deref(), dry_run),
+        Commands::UninstallPlugin { purge } => uninstall_plugin(config_dir, purge, dry_run),
+        Commands::Status { json, path } => status(config_dir, path.as_deref(), pipe_name, &cli_config, json),
+        Commands::Version { json } => version(json),
+        Commands::Doctor => doctor(config_dir),
+        Commands::Test => self_test(pipe_name, &cli_config),
+        Commands::History { tail, json } => history(tail, json),
+        Commands::Logs { follow, lines, file } => logs(config_dir, file.as_deref(), lines, follow),
+        Commands::Config { command: None } => config(config_dir, &cli_config),
+        Commands::Config { command: Some(ConfigCommands::Show { json }) } =>
+            config_show(config_dir, pipe_name, &cli_config, json),
+        Commands::InstallConfig => install_config(config_dir, &cli_config, dry_run),
+        Commands::Mute { tab } => mute(config_dir, &tab, dry_run),
+        Commands::Preset { command } => match command {
+            PresetCommands::List => preset_list(config_dir),
+            PresetCommands::Add { name, emoji } => preset_add(config_dir, &cli_config, &name, &emoji, dry_run),
+            PresetCommands::Remove { name } => preset_remove(config_dir, &name, dry_run),
         },
-        Commands::Notify { name } => notify(&name),
-        Commands::InstallPlugin => install_plugin(),
-        Commands::Status => status(),
-        Commands::Config => config(),
+        Commands::ValidatePresets { json } => validate_presets(&json),
+        Commands::Completions { shell } => completions(shell),
     }
 }
 
-fn claude_install_hooks() -> Result<()> {
+fn claude_install_hooks(events: &[String], backup: bool, force: bool, dry_run: bool) -> Result<()> {
+    let selected = select_claude_hooks(events)?;
     let claude_settings = get_claude_settings_path()?;
 
-    // Read existing settings or create new
-    let mut settings: Value = if claude_settings.exists() {
-        let content = fs::read_to_string(&claude_settings)
-            .context("Failed to read Claude settings")?;
-        serde_json::from_str(&content)
-            .context("Failed to parse Claude settings JSON")?
-    } else {
-        json!({})
-    };
-
-    // Ensure hooks object exists
-    if !settings.get("hooks").is_some() {
-        settings["hooks"] = json!({});
-    }
-
-    let hooks = settings["hooks"].as_object_mut()
-        .context("hooks is not an object")?;
-
-    // Add our hooks
-    hooks.insert(
-        "Notification".to_string(),
-        json!([{
-            "matcher": "",
-            "hooks": [{
-                "type": "command",
-                "command": "znotify notify notification"
-            }]
-        }])
-    );
-
-    hooks.insert(
-        "Stop".to_string(),
-        json!([{
-            "matcher": "",
-            "hooks": [{
-                "type": "command",
-                "command": "znotify notify stop"
-            }]
-        }])
-    );
-
-    hooks.insert(
-        "PostToolUse".to_string(),
-        json!([{
-            "matcher": "",
-            "hooks": [{
-                "type": "command",
-                "command": "znotify notify posttooluse"
-            }]
-        }])
-    );
+    // Read existing settings or create new; a broken file is only replaced when asked to
+    let mut backed_up = false;
+    let mut settings: Value = match read_claude_settings(&claude_settings)? {
+        ClaudeSettings::Missing => json!({}),
+        ClaudeSettings::Valid(settings) => settings,
+        ClaudeSettings::Corrupt(e) if force => {
+            // Backed up even with --no-backup: the broken file is the only copy of its contents
+            eprintln!("⚠️  {} is not valid JSON ({}), starting from an empty file", claude_settings.display(), e);
+            backup_file(&claude_settings, dry_run)?;
+            backed_up = true;
+            json!({})
+        }
+        ClaudeSettings::Corrupt(e) => bail!(
+            "{} is not valid JSON ({}). Fix it, or run `znotify claude install-hooks --force` to back it up and start from an empty file",
+            claude_settings.display(), e),
+    };
+
+    // Add our hooks next to whatever the user already has for each event
+    let mut added = Vec::new();
+    let mut present = Vec::new();
+    for (event, name) in &selected {
+        if hooks::install_command_hook(&mut settings, event, &hooks::hook_command(name))? {
+            added.push(*event);
+        } else {
+            present.push(*event);
+        }
+    }
+
+    if added.is_empty() {
+        info!("✅ Claude hooks already installed in {}", claude_settings.display());
+        return Ok(());
+    }
 
     // Write back
+    if backup && !backed_up {
+        backup_file(&claude_settings, dry_run)?;
+    }
+    let added = added.join(", ");
+
+    if dry_run {
+        println!("Would write {}:", claude_settings.display());
+        println!("{}", serde_json::to_string_pretty(&settings)?);
+        println!("   Would add: {}", added);
+        return Ok(());
+    }
+
     fs::create_dir_all(claude_settings.parent().unwrap())
         .context("Failed to create .claude directory")?;
     fs::write(&claude_settings, serde_json::to_string_pretty(&settings)?)
         .context("Failed to write Claude settings")?;
 
-    println!("✅ Claude hooks installed to {}", claude_settings.display());
-    println!("   Added: Notification, Stop, PostToolUse");
+    info!("✅ Claude hooks installed to {}", claude_settings.display());
+    info!("   Added: {}", added);
+    if !present.is_empty() {
+        info!("   Already present: {}", present.join(", "));
+    }
     Ok(())
 }
 
-fn claude_uninstall_hooks() -> Result<()> {
+// Resolve --events into (event, notification name) pairs; empty means the default set
+fn select_claude_hooks(events: &[String]) -> Result<Vec<(&'static str, &'static str)>> {
+    if events.is_empty() {
+        return Ok(CLAUDE_HOOKS.iter()
+            .filter(|(_, _, default)| *default)
+            .map(|(event, name, _)| (*event, *name))
+            .collect());
+    }
+
+    let mut selected = Vec::new();
+    for requested in events {
+        let Some((event, name, _)) = CLAUDE_HOOKS.iter()
+            .find(|(event, _, _)| event.eq_ignore_ascii_case(requested.trim())) else {
+            bail!("Unknown hook event: '{}'. Available: {}",
+                  requested,
+                  CLAUDE_HOOKS.iter().map(|(e, _, _)| *e).collect::<Vec<_>>().join(", "));
+        };
+        if !selected.iter().any(|(e, _)| e == event) {
+            selected.push((*event, *name));
+        }
+    }
+    Ok(selected)
+}
+
+fn claude_uninstall_hooks(backup: bool, dry_run: bool) -> Result<()> {
     let claude_settings = get_claude_settings_path()?;
 
     if !claude_settings.exists() {
-        println!("No Claude settings file found");
+        info!("No Claude settings file found");
         return Ok(());
     }
 
-    let content = fs::read_to_string(&claude_settings)
-        .context("Failed to read Claude settings")?;
-    let mut settings: Value = serde_json::from_str(&content)
-        .context("Failed to parse Claude settings JSON")?;
+    let mut settings = match read_claude_settings(&claude_settings)? {
+        ClaudeSettings::Valid(settings) => settings,
+        ClaudeSettings::Missing => json!({}),
+        ClaudeSettings::Corrupt(e) => bail!("{} is not valid JSON ({}); fix it before removing hooks",
+                                            claude_settings.display(), e),
+    };
 
     if let Some(hooks) = settings.get_mut("hooks").and_then(|h| h.as_object_mut()) {
-        hooks.remove("Notification");
-        hooks.remove("Stop");
-        hooks.remove("PostToolUse");
+        let (removed, missing) = remove_znotify_hooks(hooks);
+
+        if removed.is_empty() {
+            info!("No znotify hooks found in Claude settings");
+            return Ok(());
+        }
+        let removed = removed.join(", ");
+
+        if backup {
+            backup_file(&claude_settings, dry_run)?;
+        }
+
+        if dry_run {
+            println!("Would write {}:", claude_settings.display());
+            println!("{}", serde_json::to_string_pretty(&settings)?);
+            println!("   Would remove: {}", removed);
+            return Ok(());
+        }
 
         fs::write(&claude_settings, serde_json::to_string_pretty(&settings)?)
             .context("Failed to write Claude settings")?;
 
-        println!("✅ Claude hooks removed from {}", claude_settings.display());
+        info!("✅ Claude hooks removed from {}", claude_settings.display());
+        info!("   Removed: {}", removed);
+        if !missing.is_empty() {
+            info!("   Not installed: {}", missing.join(", "));
+        }
     } else {
-        println!("No hooks found in Claude settings");
+        info!("No hooks found in Claude settings");
     }
 
     Ok(())
 }
 
-fn notify(name: &str) -> Result<()> {
-    // Look up emoji for this notification name
-    let presets: HashMap<&str, &str> = NOTIFY_CONFIG.iter().copied().collect();
-
-    if !presets.contains_key(name) {
-        bail!("Unknown notification name: '{}'. Available: {}",
-              name,
-              NOTIFY_CONFIG.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(", "));
+// Remove only our commands; a group or event goes away once nothing is left in it.
+// Returns the events that had znotify commands and the ones that didn't
+fn remove_znotify_hooks(hooks: &mut Map<String, Value>) -> (Vec<&'static str>, Vec<&'static str>) {
+    let mut removed = Vec::new();
+    let mut missing = Vec::new();
+    for (event, _, _) in CLAUDE_HOOKS {
+        let Some(groups) = hooks.get_mut(*event).and_then(|g| g.as_array_mut()) else {
+            missing.push(*event);
+            continue;
+        };
+        let mut count = 0;
+        for group in groups.iter_mut() {
+            if let Some(commands) = group.get_mut("hooks").and_then(|h| h.as_array_mut()) {
+                let before = commands.len();
+                commands.retain(|hook| {
+                    !hook.get("command").and_then(|c| c.as_str()).is_some_and(hooks::is_znotify_command)
+                });
+                count += before - commands.len();
+            }
+        }
+        groups.retain(|group| {
+            group.get("hooks").and_then(|h| h.as_array()).is_none_or(|h| !h.is_empty())
+        });
+        if groups.is_empty() {
+            hooks.remove(*event);
+        }
+        if count == 0 {
+            missing.push(*event);
+        } else {
+            removed.push(*event);
+        }
     }
+    (removed, missing)
+}
 
-    // Get Zellij environment variables
-    let pane_id = env::var("ZELLIJ_PANE_ID")
-        .context("ZELLIJ_PANE_ID not found. Are you running inside Zellij?")?;
-    let session_name = env::var("ZELLIJ_SESSION_NAME").unwrap_or_default();
-    let tab_name = env::var("ZELLIJ_TAB_NAME").unwrap_or_default();
+fn hooks_print(event: &str, cli_config: &CliConfig) -> Result<()> {
+    println!("{}", hooks::hook_command(&hook_notification(event, cli_config)?));
+    Ok(())
+}
 
-    // Build and execute zellij pipe command
-    let mut cmd = Command::new("zellij");
-    cmd.arg("pipe")
-        .arg("-n")
-        .arg("notify")
-        .arg("-a")
-        .arg(format!("pane_id={}", pane_id));
+fn hooks_install(
+    event: &str,
+    cli_config: &CliConfig,
+    path: &Path,
+    pointer: &str,
+    format: Option<hooks::Format>,
+    backup: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let command = hooks::hook_command(&hook_notification(event, cli_config)?);
+    let format = format.unwrap_or_else(|| match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => hooks::Format::Toml,
+        _ => hooks::Format::Json,
+    });
 
-    if !session_name.is_empty() {
-        cmd.arg("-a").arg(format!("session_name={}", session_name));
-    }
+    let content = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let mut settings = hooks::parse(&content, format)
+        .with_context(|| format!("Failed to load {}", path.display()))?;
 
-    if !tab_name.is_empty() {
-        cmd.arg("-a").arg(format!("tab_name={}", tab_name));
+    if !hooks::splice_command(&mut settings, pointer, &command)? {
+        info!("✅ '{}' already installed at {} in {}", command, pointer, path.display());
+        return Ok(());
     }
+    let rendered = hooks::render(&settings, format)?;
 
-    cmd.arg(name);
+    if backup {
+        backup_file(path, dry_run)?;
+    }
 
-    let output = cmd.output()
-        .context("Failed to execute zellij pipe command")?;
+    if dry_run {
+        println!("Would write {}:", path.display());
+        println!("{}", rendered);
+        return Ok(());
+    }
 
-    if !output.status.success() {
-        bail!("zellij pipe failed: {}", String::from_utf8_lossy(&output.stderr));
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
     }
+    fs::write(path, rendered)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
 
+    info!("✅ Added '{}' at {} in {}", command, pointer, path.display());
     Ok(())
 }
 
-fn install_plugin() -> Result<()> {
-    let plugin_dir = get_plugin_path()?.parent().unwrap().to_path_buf();
-    let plugin_path = get_plugin_path()?;
+// Accepts a notification name or a Claude hook event, which maps to its notification
+fn hook_notification(event: &str, cli_config: &CliConfig) -> Result<String> {
+    let event = event.trim();
+    if let Some((_, name, _)) = CLAUDE_HOOKS.iter().find(|(e, _, _)| e.eq_ignore_ascii_case(event)) {
+        return Ok(name.to_string());
+    }
+
+    let notifications = notifications(cli_config);
+    if notifications.iter().any(|(name, _)| name == event) {
+        return Ok(event.to_string());
+    }
+    bail!("Unknown notification name: '{}'. Available: {}",
+          event,
+          notifications.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>().join(", "));
+}
+
+// Claude's settings.json as found on disk
+enum ClaudeSettings {
+    Missing,
+    Valid(Value),
+    Corrupt(serde_json::Error),  // Present but not JSON, e.g. after a bad hand edit
+}
 
-    fs::create_dir_all(&plugin_dir)
-        .context("Failed to create plugin directory")?;
+fn read_claude_settings(path: &Path) -> Result<ClaudeSettings> {
+    if !path.exists() {
+        return Ok(ClaudeSettings::Missing);
+    }
+    let content = fs::read_to_string(path)
+        .context("Failed to read Claude settings")?;
+    Ok(match serde_json::from_str(&content) {
+        Ok(settings) => ClaudeSettings::Valid(settings),
+        Err(e) => ClaudeSettings::Corrupt(e),
+    })
+}
 
-    fs::write(&plugin_path, WASM_BYTES)
-        .context("Failed to write plugin file")?;
+// Copy a file to <file>.bak.<unixtime> before we overwrite it; skipped for missing or empty files
+fn backup_file(path: &Path, dry_run: bool) -> Result<Option<PathBuf>> {
+    let is_empty = fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true);
+    if is_empty {
+        return Ok(None);
+    }
 
-    println!("✅ Plugin installed to {}", plugin_path.display());
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut backup_name = path.file_name().unwrap_or_default().to_os_string();
+    backup_name.push(format!(".bak.{}", timestamp));
+    let backup_path = path.with_file_name(backup_name);
 
-    // Try to reload plugin if in Zellij
-    if env::var("ZELLIJ").is_ok() {
-        let reload_result = Command::new("zellij")
-            .arg("action")
-            .arg("start-or-reload-plugin")
-            .arg(format!("file:{}", plugin_path.display()))
-            .output();
+    if dry_run {
+        println!("📦 Would back up {} to {}", path.display(), backup_path.display());
+        return Ok(Some(backup_path));
+    }
 
-        match reload_result {
-            Ok(output) if output.status.success() => {
-                println!("✅ Plugin reloaded in Zellij");
-            }
-            _ => {
-                println!("⚠️  Could not reload plugin automatically. Restart Zellij or run:");
-                println!("   zellij action start-or-reload-plugin file:{}", plugin_path.display());
-            }
+    fs::copy(path, &backup_path)
+        .with_context(|| format!("Failed to back up {}", path.display()))?;
+
+    info!("📦 Backed up {} to {}", path.display(), backup_path.display());
+    Ok(Some(backup_path))
+}
+
+fn notify(args: &NotifyArgs, config_dir: Option<&Path>, pipe_name: &str, cli_config: &CliConfig) -> Result<()> {
+    VERBOSE.store(args.verbose, Ordering::Relaxed);
+    if args.list {
+        return list_notifications(cli_config, args.json);
+    }
+    // clap only lets a missing name through together with --list
+    let name = args.name.as_deref().unwrap_or_default();
+    let notifications = notifications(cli_config);
+
+    // An explicit --emoji or --exit wins over the preset table, so any name is allowed with it
+    let override_emoji = override_emoji(args);
+    let emoji = match override_emoji {
+        Some(emoji) => emoji,
+        None => {
+            // Look up emoji for this notification name
+            let presets: HashMap<&str, &str> = notifications.iter()
+                .map(|(n, e)| (n.as_str(), e.as_str()))
+                .collect();
+
+            let Some(emoji) = presets.get(name) else {
+                bail!("Unknown notification name: '{}'. Available: {}",
+                      name,
+                      notifications.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>().join(", "));
+            };
+            *emoji
+        }
+    };
+
+    let desktop_fallback = args.desktop_fallback
+        || env_flag("ZNOTIFY_DESKTOP_FALLBACK")
+        || cli_config.desktop_fallback;
+    if desktop_fallback && args.session.is_none() && env::var("ZELLIJ").is_err() {
+        desktop_notify(name, emoji, args.message.as_deref())?;
+        record_history(args, emoji, cli_config);
+        if args.echo {
+            println!("notified {} ({}) -> desktop", name, emoji);
         }
+        return Ok(());
+    }
+
+    if let Some(plugin_path) = missing_plugin(args, config_dir, cli_config) {
+        ensure_plugin_installed(config_dir, &plugin_path);
+    }
+
+    let extra_args = notify_pipe_args(args, override_emoji);
+
+    let session = args.session.as_deref().filter(|s| !s.is_empty());
+    if let Some(session) = session {
+        warn_unknown_session(session);
+    }
+
+    let retry_delay = Duration::from_millis(args.retry_delay_ms);
+    send_pipe_with_retry(pipe_name, name, session, &extra_args, args.attempts, retry_delay)?;
+    record_history(args, emoji, cli_config);
+    if args.echo {
+        let current_session = env::var("ZELLIJ_SESSION_NAME").ok();
+        println!("{}", echo_line(name, emoji, args, env::var("ZELLIJ_PANE_ID").ok().as_deref(),
+                                 session.or(current_session.as_deref()), env::var("ZELLIJ_TAB_NAME").ok().as_deref()));
     }
 
+    if rings_bell(args, name, cli_config) {
+        ring_bell();
+    }
     Ok(())
 }
 
-fn status() -> Result<()> {
-    println!("znotify status\n");
+// The pipe args carrying notify's flags; targeting by pane and session is build_pipe_command's
+fn notify_pipe_args(args: &NotifyArgs, override_emoji: Option<&str>) -> Vec<(&'static str, String)> {
+    let mut extra_args = Vec::new();
+    // The plugin treats an `emoji` arg as a raw emoji and skips its own preset lookup
+    if let Some(emoji) = override_emoji {
+        extra_args.push(("emoji", emoji.to_string()));
+    }
+    if let Some(message) = args.message.as_deref().filter(|m| !m.is_empty()) {
+        extra_args.push(("message", message.to_string()));
+    }
+    if let Some(agent) = args.agent.as_deref().filter(|a| !a.is_empty()) {
+        extra_args.push(("agent", agent.to_string()));
+    }
+    if let Some(set_name) = args.set_name.as_deref().filter(|n| !n.is_empty()) {
+        extra_args.push(("set_name", set_name.to_string()));
+    }
+    if args.all {
+        extra_args.push(("target", "all".to_string()));
+    }
+    if args.skip_active {
+        extra_args.push(("skip_active", "true".to_string()));
+    }
+    if !args.tabs.is_empty() {
+        let positions: Vec<String> = args.tabs.iter().map(|p| p.to_string()).collect();
+        extra_args.push(("tab_positions", positions.join(",")));
+    }
+    if let Some(number) = args.tab {
+        extra_args.push(("tab_number", number.to_string()));
+    }
+    extra_args
+}
 
-    // Check plugin installation
-    let plugin_path = get_plugin_path()?;
-    let plugin_installed = plugin_path.exists();
-    println!("Plugin: {}", if plugin_installed {
-        format!("✅ Installed at {}", plugin_path.display())
-    } else {
-        format!("❌ Not installed (run: znotify install-plugin)")
-    });
+// The names `notify` accepts, for wrappers that check a name before sending it
+fn list_notifications(cli_config: &CliConfig, json_output: bool) -> Result<()> {
+    print!("{}", notification_list(cli_config, json_output)?);
+    Ok(())
+}
 
-    // Check Claude hooks
-    let claude_settings = get_claude_settings_path()?;
-    let hooks_installed = if claude_settings.exists() {
-        let content = fs::read_to_string(&claude_settings).ok();
-        content.and_then(|c| serde_json::from_str::<Value>(&c).ok())
-            .and_then(|s| s.get("hooks").cloned())
-            .and_then(|h| {
-                let has_notification = h.get("Notification").is_some();
-                let has_stop = h.get("Stop").is_some();
-                let has_posttooluse = h.get("PostToolUse").is_some();
-                Some(has_notification || has_stop || has_posttooluse)
-            })
-            .unwrap_or(false)
+// `name<TAB>emoji` lines, or a JSON array of {name, emoji}
+fn notification_list(cli_config: &CliConfig, json_output: bool) -> Result<String> {
+    let notifications = notifications(cli_config);
+    if json_output {
+        let list: Vec<Value> = notifications.iter()
+            .map(|(name, emoji)| json!({"name": name, "emoji": emoji}))
+            .collect();
+        return Ok(format!("{}\n", serde_json::to_string_pretty(&list)?));
+    }
+
+    Ok(notifications.iter()
+        .map(|(name, emoji)| format!("{}\t{}\n", name, emoji))
+        .collect())
+}
+
+// "notified stop (✅) -> pane 4 session main tab build"; the target follows the flags the
+// same way the pipe args do, and unknown parts show as ?
+fn echo_line(name: &str, emoji: &str, args: &NotifyArgs, pane: Option<&str>, session: Option<&str>, tab: Option<&str>) -> String {
+    let known = |value: Option<&str>| value.filter(|v| !v.is_empty()).unwrap_or("?").to_string();
+    let target = if args.session.is_some() {
+        "active tab".to_string()
+    } else if args.all {
+        "all tabs".to_string()
+    } else if !args.tabs.is_empty() {
+        let positions: Vec<String> = args.tabs.iter().map(|p| p.to_string()).collect();
+        format!("tabs {}", positions.join(","))
+    } else if let Some(number) = args.tab {
+        format!("tab #{}", number)
     } else {
-        false
+        format!("pane {}", known(pane))
     };
 
-    println!("Claude hooks: {}", if hooks_installed {
-        format!("✅ Installed at {}", claude_settings.display())
-    } else {
-        format!("❌ Not installed (run: znotify claude install-hooks)")
-    });
+    let mut line = format!("notified {} ({}) -> {} session {}", name, emoji, target, known(session));
+    if target.starts_with("pane ") {
+        line.push_str(&format!(" tab {}", known(tab)));
+    }
+    line
+}
 
-    // Check if in Zellij session
-    let in_zellij = env::var("ZELLIJ").is_ok();
-    println!("Zellij session: {}", if in_zellij {
-        "✅ Running in Zellij"
+// First-run self-healing for hooks: install the plugin when its file is missing. A failed
+// install only warns, so the notification is still attempted
+fn ensure_plugin_installed(config_dir: Option<&Path>, plugin_path: &Path) {
+    trace!("{} is missing, installing the plugin", plugin_path.display());
+    if let Err(e) = install_plugin(config_dir, None, false) {
+        eprintln!("⚠️  Could not install the plugin to {}: {:#}", plugin_path.display(), e);
+    }
+}
+
+// Where auto-install should write the plugin: only with --auto-install (or its config.toml
+// setting), inside Zellij, and while the file isn't there yet
+fn missing_plugin(args: &NotifyArgs, config_dir: Option<&Path>, cli_config: &CliConfig) -> Option<PathBuf> {
+    if !(args.auto_install || cli_config.auto_install) || env::var("ZELLIJ").is_err() {
+        return None;
+    }
+    get_plugin_path(config_dir).ok().filter(|path| !path.exists())
+}
+
+// --emoji, else the emoji for --exit's code
+fn override_emoji(args: &NotifyArgs) -> Option<&str> {
+    args.emoji.as_deref().filter(|e| !e.is_empty())
+        .or(args.exit.map(exit_emoji))
+}
+
+fn exit_emoji(code: i32) -> &'static str {
+    if code == 0 { EXIT_SUCCESS_EMOJI } else { EXIT_FAILURE_EMOJI }
+}
+
+// Append the notification to the history file when asked to. The notification itself
+// already went out, so a failed write only warns
+fn record_history(args: &NotifyArgs, emoji: &str, cli_config: &CliConfig) {
+    if !(args.history || cli_config.history) {
+        return;
+    }
+
+    // ZELLIJ_TAB_NAME describes the pane's own tab, which isn't the target with --all, --tabs, --tab or --session
+    let own_tab = !args.all && args.tabs.is_empty() && args.tab.is_none() && args.session.is_none();
+    let entry = history::Entry {
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        name: args.name.clone().unwrap_or_default(),
+        emoji: emoji.to_string(),
+        session: args.session.clone().filter(|s| !s.is_empty())
+            .or_else(|| env::var("ZELLIJ_SESSION_NAME").ok()),
+        tab: env::var("ZELLIJ_TAB_NAME").ok().filter(|_| own_tab),
+        pane_id: env::var("ZELLIJ_PANE_ID").ok().filter(|_| own_tab),
+    };
+
+    if let Err(e) = get_history_path().and_then(|path| history::append(&path, &entry)) {
+        eprintln!("⚠️  Failed to record history: {:#}", e);
+    }
+}
+
+fn history(tail: Option<usize>, json_output: bool) -> Result<()> {
+    let path = get_history_path()?;
+    let entries = history::tail(&path, tail)?;
+
+    if entries.is_empty() && !json_output {
+        println!("No history in {}", path.display());
+        println!("Record notifications with `znotify notify --history` or `history = true` in config.toml");
+    }
+    for entry in &entries {
+        if json_output {
+            println!("{}", serde_json::to_string(entry)?);
+            continue;
+        }
+        let place = match (&entry.session, &entry.tab) {
+            (Some(session), Some(tab)) => format!("{}/{}", session, tab),
+            (Some(session), None) => session.clone(),
+            (None, Some(tab)) => tab.clone(),
+            (None, None) => "-".to_string(),
+        };
+        println!("{}  {} {}  {}", history::format_utc(entry.timestamp), entry.emoji, entry.name, place);
+    }
+    Ok(())
+}
+
+// --bell, $ZNOTIFY_BELL, or the name listed under `bell` in config.toml
+fn rings_bell(args: &NotifyArgs, name: &str, cli_config: &CliConfig) -> bool {
+    args.bell || env_flag("ZNOTIFY_BELL") || cli_config.bell.iter().any(|n| n == name)
+}
+
+// BEL to the controlling terminal; hooks often run without one, so fall back to stderr
+fn ring_bell() {
+    let written = fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .and_then(|mut tty| write_bell(&mut tty));
+    if written.is_err() {
+        let _ = write_bell(&mut io::stderr());
+    }
+}
+
+fn write_bell(out: &mut impl Write) -> io::Result<()> {
+    out.write_all(b"\x07")
+}
+
+// Fallback for hooks that fire outside Zellij: a native desktop notification
+fn desktop_notify(name: &str, emoji: &str, text: Option<&str>) -> Result<()> {
+    let message = match text {
+        Some(text) => format!("{} {}: {}", emoji, name, text),
+        None => format!("{} {}", emoji, name),
+    };
+
+    let (tool, mut cmd) = if cfg!(target_os = "macos") {
+        let mut cmd = Command::new("osascript");
+        cmd.arg("-e").arg(format!(
+            "display notification \"{}\" with title \"znotify\"",
+            message.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+        ("osascript", cmd)
+    } else if cfg!(target_os = "linux") {
+        let mut cmd = Command::new("notify-send");
+        cmd.arg("znotify").arg(&message);
+        ("notify-send", cmd)
     } else {
-        "❌ Not in Zellij session"
-    });
+        bail!("Desktop notifications are not supported on this platform");
+    };
 
-    // Show available notifications
-    println!("\nAvailable notifications:");
-    for (name, emoji) in NOTIFY_CONFIG {
-        println!("  {} {}", emoji, name);
+    let output = match cmd.output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            bail!("{} not found; install it or run inside Zellij", tool);
+        }
+        Err(e) => return Err(e).context(format!("Failed to execute {}", tool)),
+    };
+
+    if !output.status.success() {
+        bail!("{} failed: {}", tool, String::from_utf8_lossy(&output.stderr));
     }
 
     Ok(())
 }
 
-fn config() -> Result<()> {
-    println!("Add this to your Zellij config (~/.config/zellij/config.kdl):\n");
-    println!("{}", ZELLIJ_CONFIG_TEMPLATE);
+// True when an env var is set to 1/true/yes
+fn env_flag(name: &str) -> bool {
+    env::var(name)
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+fn clear(pipe_name: &str, emoji: Option<&str>) -> Result<()> {
+    send_pipe(&format!("{}-clear", pipe_name), "", None, &clear_args(emoji))
+}
+
+fn clear_args(emoji: Option<&str>) -> Vec<(&'static str, String)> {
+    emoji.filter(|e| !e.is_empty())
+        .map(|e| ("emoji", e.to_string()))
+        .into_iter()
+        .collect()
+}
+
+// The plugin warns in its log when no such tab exists
+fn goto(pipe_name: &str, tab: &str) -> Result<()> {
+    send_pipe(&format!("{}-goto", pipe_name), "", None, &[goto_target(tab)])
+}
+
+// A number is the 0-indexed position `list` prints, anything else a tab name. The position
+// goes out as a 1-based tab_number, so the plugin's tab_position_base can't shift it.
+fn goto_target(tab: &str) -> (&'static str, String) {
+    match tab.parse::<usize>() {
+        Ok(position) => ("tab_number", (position + 1).to_string()),
+        Err(_) => ("tab_name", tab.to_string()),
+    }
+}
+
+// Round trip through the plugin: notify this tab, see it decorated, clear it, see it clean
+fn self_test(pipe_name: &str, cli_config: &CliConfig) -> Result<()> {
+    if env::var("ZELLIJ").is_err() {
+        bail!("Not in a Zellij session; run znotify test from a Zellij pane");
+    }
+    let Some(emoji) = test_emoji(&notifications(cli_config)) else {
+        bail!("Every test emoji ({}) is used by a preset; rename one of them", TEST_EMOJIS.join(" "));
+    };
+
+    send_pipe(pipe_name, "test", None, &[("emoji", emoji.to_string())])?;
+    let decorated = wait_for(pipe_name, |pending| pending.iter().find(|n| n.emoji.starts_with(emoji)).map(|n| n.position))
+        .context("The plugin didn't answer, so it's likely not installed or not loaded. Check with: znotify doctor")?;
+    let Some(position) = decorated else {
+        bail!("The plugin is loaded but didn't decorate this tab with {} within {}s. \
+               Check that this tab isn't muted and that skip_if_focused is off, or look at: znotify logs",
+              emoji, TEST_TIMEOUT.as_secs());
+    };
+    info!("✅ Tab {} was decorated with {}", position, emoji);
+
+    send_pipe(&format!("{}-clear", pipe_name), "", None, &[])?;
+    let remaining = wait_for(pipe_name, |pending| {
+        (!pending.iter().any(|n| n.position == position && n.emoji.starts_with(emoji))).then_some(())
+    })?;
+    if remaining.is_none() {
+        bail!("Tab {} still shows {} after clearing; look at: znotify logs", position, emoji);
+    }
+    info!("✅ Tab {} was cleared", position);
     Ok(())
 }
 
-fn get_claude_settings_path() -> Result<PathBuf> {
-    let home = env::var("HOME")
-        .context("HOME environment variable not set")?;
-    Ok(PathBuf::from(home).join(".claude").join("settings.json"))
+// The first test emoji that no preset uses, so the test can't be mistaken for a real notification
+fn test_emoji(notifications: &[(String, String)]) -> Option<&'static str> {
+    TEST_EMOJIS.iter()
+        .copied()
+        .find(|candidate| !notifications.iter().any(|(_, emoji)| emoji == candidate))
 }
 
-fn get_plugin_path() -> Result<PathBuf> {
-    let home = env::var("HOME")
-        .context("HOME environment variable not set")?;
-    Ok(PathBuf::from(home)
-        .join(".config")
-        .join("zellij")
-        .join("plugins")
-        .join("zellij-notify.wasm"))
+// Query the plugin until `check` finds what it's looking for, or TEST_TIMEOUT passes
+fn wait_for<T>(pipe_name: &str, check: impl Fn(&[PendingNotification]) -> Option<T>) -> Result<Option<T>> {
+    let started = Instant::now();
+    loop {
+        if let Some(found) = check(&pending_notifications(pipe_name)?) {
+            return Ok(Some(found));
+        }
+        if started.elapsed() >= TEST_TIMEOUT {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+// One entry of the plugin's query reply
+#[derive(Deserialize)]
+struct PendingNotification {
+    position: usize,
+    name: String,
+    emoji: String,
+}
+
+fn list(pipe_name: &str, json_output: bool) -> Result<()> {
+    let reply = query_pipe(&format!("{}-query", pipe_name))?;
+
+    if json_output {
+        println!("{}", reply.trim());
+        return Ok(());
+    }
+
+    let pending = parse_pending(&reply)?;
+    if pending.is_empty() {
+        println!("No notifications");
+    }
+    for notification in &pending {
+        println!("{} {} {}", notification.position, notification.emoji, notification.name);
+    }
+    Ok(())
+}
+
+// Tabs the plugin reports as showing a notification
+fn pending_notifications(pipe_name: &str) -> Result<Vec<PendingNotification>> {
+    parse_pending(&query_pipe(&format!("{}-query", pipe_name))?)
+}
+
+fn parse_pending(reply: &str) -> Result<Vec<PendingNotification>> {
+    serde_json::from_str(reply.trim()).context("Failed to parse the plugin's reply")
+}
+
+// Send a pipe message and return what the plugin writes back, giving up after QUERY_TIMEOUT
+fn query_pipe(pipe_name: &str) -> Result<String> {
+    let mut child = build_pipe_command(pipe_name, "", None, &[])?
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(zellij_error)?;
+
+    // `zellij pipe` stays open until a plugin answers, which never happens if ours isn't loaded
+    let started = Instant::now();
+    while child.try_wait()?.is_none() {
+        if started.elapsed() >= QUERY_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("No reply from the plugin within {}s. Is it loaded? Check with: znotify doctor",
+                  QUERY_TIMEOUT.as_secs());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("zellij pipe failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Send a pipe message to the plugin, tagged with this pane's id so it lands on the right tab
+fn send_pipe(pipe_name: &str, payload: &str, session: Option<&str>, extra_args: &[(&str, String)]) -> Result<()> {
+    send_pipe_with_retry(pipe_name, payload, session, extra_args, 1, Duration::ZERO)
+}
+
+// send_pipe, trying up to `attempts` times while the failure looks transient
+fn send_pipe_with_retry(
+    pipe_name: &str,
+    payload: &str,
+    session: Option<&str>,
+    extra_args: &[(&str, String)],
+    attempts: u32,
+    delay: Duration,
+) -> Result<()> {
+    let mut cmd = build_pipe_command(pipe_name, payload, session, extra_args)?;
+    with_retry(attempts, delay, || run_pipe(&mut cmd))
+}
+
+// Why a `zellij pipe` attempt failed, and whether trying again can help
+enum PipeFailure {
+    Transient(anyhow::Error),  // Zellij starting up or busy
+    Fatal(anyhow::Error),  // Missing binary, unknown session: the same every time
+}
+
+// One `zellij pipe` run, sorting its failures into ones worth retrying and ones that aren't
+fn run_pipe(cmd: &mut Command) -> Result<(), PipeFailure> {
+    trace!("Running: {}", command_line(cmd));
+    let output = cmd.output().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => PipeFailure::Fatal(zellij_error(e)),
+        _ => PipeFailure::Transient(zellij_error(e)),
+    })?;
+    trace!("zellij {}", output.status);
+    trace!("stdout: {:?}", String::from_utf8_lossy(&output.stdout));
+    trace!("stderr: {:?}", String::from_utf8_lossy(&output.stderr));
+
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let error = anyhow!("zellij pipe failed: {}", stderr);
+    let lower = stderr.to_lowercase();
+    if lower.contains("not found") || lower.contains("no active session") {
+        return Err(PipeFailure::Fatal(error));
+    }
+    Err(PipeFailure::Transient(error))
+}
+
+// A command as it would be typed in a shell, for --verbose
+fn command_line(cmd: &Command) -> String {
+    let words = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|word| {
+            let word = word.to_string_lossy();
+            let plain = !word.is_empty() && word.chars().all(|c| c.is_alphanumeric() || "-_=./:,@%+".contains(c));
+            if plain {
+                word.into_owned()
+            } else {
+                format!("'{}'", word.replace('\'', "'\\''"))
+            }
+        });
+    words.collect::<Vec<_>>().join(" ")
+}
+
+// Call `attempt` until it succeeds, fails for good, or `attempts` runs are used up,
+// sleeping `delay` before the first retry and twice as long before each next one
+fn with_retry<T>(attempts: u32, delay: Duration, mut attempt: impl FnMut() -> Result<T, PipeFailure>) -> Result<T> {
+    let mut delay = delay;
+    let mut tried = 1;
+    loop {
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(PipeFailure::Fatal(e)) => return Err(e),
+            Err(PipeFailure::Transient(e)) if tried >= attempts => return Err(e),
+            Err(PipeFailure::Transient(e)) => {
+                eprintln!("⚠️  {} (attempt {}/{}), retrying in {}ms",
+                          format!("{:#}", e).trim_end(), tried, attempts, delay.as_millis());
+                thread::sleep(delay);
+                delay *= 2;
+                tried += 1;
+            }
+        }
+    }
+}
+
+// Best effort: a typo'd --session would otherwise go nowhere without a word
+fn warn_unknown_session(session: &str) {
+    let Ok(output) = Command::new("zellij").args(["list-sessions", "--short"]).output() else {
+        return;
+    };
+    if !output.status.success() {
+        return;
+    }
+    let sessions = String::from_utf8_lossy(&output.stdout);
+    if !sessions.lines().any(|line| line.trim() == session) {
+        eprintln!("⚠️  Session '{}' not found in `zellij list-sessions`", session);
+    }
+}
+
+// A failed `zellij` launch; the missing-binary case gets an actionable message
+fn zellij_error(e: io::Error) -> anyhow::Error {
+    if e.kind() == io::ErrorKind::NotFound {
+        anyhow!(ZELLIJ_NOT_FOUND)
+    } else {
+        anyhow::Error::new(e).context("Failed to execute zellij pipe command")
+    }
+}
+
+// `session` targets another session; our pane and tab mean nothing there, so its active tab is used
+fn build_pipe_command(
+    pipe_name: &str,
+    payload: &str,
+    session: Option<&str>,
+    extra_args: &[(&str, String)],
+) -> Result<Command> {
+    let current_session = env::var("ZELLIJ_SESSION_NAME").unwrap_or_default();
+    let other_session = session.filter(|s| *s != current_session);
+    trace!("ZELLIJ_PANE_ID={:?} ZELLIJ_SESSION_NAME={:?} ZELLIJ_TAB_NAME={:?}",
+           env::var("ZELLIJ_PANE_ID").ok(), env::var("ZELLIJ_SESSION_NAME").ok(), env::var("ZELLIJ_TAB_NAME").ok());
+    if let Some(session) = other_session {
+        trace!("Targeting session '{}': its active tab, not this pane", session);
+    }
+
+    let mut cmd = Command::new("zellij");
+    if let Some(session) = other_session {
+        cmd.arg("--session")
+            .arg(session)
+            .arg("pipe")
+            .arg("-n")
+            .arg(pipe_name)
+            .arg("-a")
+            .arg(format!("session_name={}", session));
+    } else if extra_args.iter().any(|(key, _)| EXPLICIT_TARGET_ARGS.contains(key)) {
+        trace!("Explicit target given, not sending this pane's id");
+        cmd.arg("pipe")
+            .arg("-n")
+            .arg(pipe_name);
+
+        if !current_session.is_empty() {
+            cmd.arg("-a").arg(format!("session_name={}", current_session));
+        }
+    } else {
+        // Get Zellij environment variables
+        let pane_id = env::var("ZELLIJ_PANE_ID")
+            .context("ZELLIJ_PANE_ID not found. Are you running inside Zellij?")?;
+        let tab_name = env::var("ZELLIJ_TAB_NAME").unwrap_or_default();
+
+        cmd.arg("pipe")
+            .arg("-n")
+            .arg(pipe_name)
+            .arg("-a")
+            .arg(format!("pane_id={}", pane_id));
+
+        if !current_session.is_empty() {
+            cmd.arg("-a").arg(format!("session_name={}", current_session));
+        }
+
+        if !tab_name.is_empty() {
+            cmd.arg("-a").arg(format!("tab_name={}", tab_name));
+        }
+    }
+
+    for (key, value) in extra_args {
+        cmd.arg("-a").arg(format!("{}={}", key, value));
+    }
+
+    // zellij pipe reads STDIN when no payload is given, so always pass one
+    cmd.arg(payload);
+
+    Ok(cmd)
+}
+
+fn install_plugin(config_dir: Option<&Path>, path: Option<&Path>, dry_run: bool) -> Result<()> {
+    if WASM_BYTES.is_empty() {
+        bail!("plugin binary not bundled in this build; build the wasm target first \
+               (cargo build --release --target wasm32-wasip1), then rebuild the CLI");
+    }
+
+    let plugin_path = resolve_plugin_path(config_dir, path)?;
+    let plugin_dir = plugin_path.parent().unwrap().to_path_buf();
+    if let Some(file) = nearest_existing(&plugin_dir).filter(|p| !p.is_dir()) {
+        bail!("Can't create {}: {} is not a directory", plugin_dir.display(), file.display());
+    }
+
+    let existing = fs::read(&plugin_path).ok();
+    let change = plugin_change(existing.as_deref(), WASM_BYTES);
+
+    if dry_run {
+        if change == PluginChange::Unchanged {
+            println!("{} is already this plugin, nothing to write", plugin_path.display());
+        } else {
+            println!("Would write {} bytes to {} ({})", WASM_BYTES.len(), plugin_path.display(), change.describe());
+        }
+        if env::var("ZELLIJ").is_ok() {
+            println!("Would reload the plugin in Zellij");
+        }
+        return Ok(());
+    }
+
+    if change == PluginChange::Unchanged {
+        info!("✅ {} is already this plugin, nothing to write", plugin_path.display());
+    } else {
+        write_plugin(&plugin_path, WASM_BYTES)?;
+        info!("✅ Plugin installed to {} ({})", plugin_path.display(), change.describe());
+    }
+
+    // Try to reload plugin if in Zellij
+    if env::var("ZELLIJ").is_ok() {
+        let reload_result = reload_command(&plugin_path).output();
+
+        match reload_result {
+            Ok(output) if output.status.success() => {
+                info!("✅ Plugin reloaded in Zellij");
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                eprintln!("⚠️  Could not reload plugin: {}", ZELLIJ_NOT_FOUND);
+            }
+            _ => {
+                eprintln!("⚠️  Could not reload plugin automatically. Restart Zellij or run:");
+                eprintln!("   zellij action start-or-reload-plugin file:{}", plugin_path.display());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_plugin(plugin_path: &Path, bytes: &[u8]) -> Result<()> {
+    let plugin_dir = plugin_path.parent().unwrap();
+    fs::create_dir_all(plugin_dir)
+        .with_context(|| format!("Failed to create plugin directory {}", plugin_dir.display()))?;
+    fs::write(plugin_path, bytes)
+        .context("Failed to write plugin file")
+}
+
+// Loads the plugin at `plugin_path` again in the running session
+fn reload_command(plugin_path: &Path) -> Command {
+    let mut cmd = Command::new("zellij");
+    cmd.arg("action")
+        .arg("start-or-reload-plugin")
+        .arg(format!("file:{}", plugin_path.display()));
+    cmd
+}
+
+// What writing `new` over the plugin file already on disk amounts to
+#[derive(PartialEq, Debug)]
+enum PluginChange {
+    Fresh,  // Nothing there yet
+    Unchanged,  // Byte-for-byte the same file
+    Upgrade { from: String, to: String },
+    Downgrade { from: String, to: String },
+    Rebuild { version: Option<String> },  // Different bytes, same or unreadable version
+}
+
+impl PluginChange {
+    fn describe(&self) -> String {
+        match self {
+            PluginChange::Fresh => "fresh install".to_string(),
+            PluginChange::Unchanged => "identical".to_string(),
+            PluginChange::Upgrade { from, to } => format!("upgrade {} → {}", from, to),
+            PluginChange::Downgrade { from, to } => format!("downgrade {} → {}", from, to),
+            PluginChange::Rebuild { version: Some(version) } => format!("different build of {}", version),
+            PluginChange::Rebuild { version: None } => "replacing a plugin of unknown version".to_string(),
+        }
+    }
+}
+
+// Compare by the version marker the plugin embeds, falling back to the bytes themselves
+fn plugin_change(existing: Option<&[u8]>, new: &[u8]) -> PluginChange {
+    let Some(existing) = existing else {
+        return PluginChange::Fresh;
+    };
+    if existing == new {
+        return PluginChange::Unchanged;
+    }
+
+    match (wasm_version(existing), wasm_version(new)) {
+        (Some(from), Some(to)) if from != to => {
+            if version_key(&to) < version_key(&from) {
+                PluginChange::Downgrade { from, to }
+            } else {
+                PluginChange::Upgrade { from, to }
+            }
+        }
+        (Some(from), Some(_)) => PluginChange::Rebuild { version: Some(from) },
+        _ => PluginChange::Rebuild { version: None },
+    }
+}
+
+// The version in a plugin wasm's "znotify-plugin-version:<version>;" marker
+fn wasm_version(wasm: &[u8]) -> Option<String> {
+    const MARKER: &[u8] = b"znotify-plugin-version:";
+    let start = wasm.windows(MARKER.len()).position(|w| w == MARKER)? + MARKER.len();
+    let len = wasm[start..].iter().take(64).position(|b| *b == b';')?;
+    let version = std::str::from_utf8(&wasm[start..start + len]).ok()?;
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+// "0.10.2" -> [0, 10, 2], so versions compare numerically; pre-release suffixes are ignored
+fn version_key(version: &str) -> Vec<u64> {
+    version.split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+// --path as an absolute file path (the reload command needs one), else the default location.
+// An existing directory gets the usual file name inside it.
+fn resolve_plugin_path(config_dir: Option<&Path>, path: Option<&Path>) -> Result<PathBuf> {
+    let Some(path) = path else {
+        return get_plugin_path(config_dir);
+    };
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().context("Failed to read the current directory")?.join(path)
+    };
+    if path.is_dir() {
+        return Ok(path.join("zellij-notify.wasm"));
+    }
+    Ok(path)
+}
+
+// `path` or its closest ancestor that exists
+fn nearest_existing(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|p| p.exists())
+}
+
+fn uninstall_plugin(config_dir: Option<&Path>, purge: bool, dry_run: bool) -> Result<()> {
+    let plugin_path = get_plugin_path(config_dir)?;
+
+    if plugin_path.exists() && dry_run {
+        println!("Would remove {}", plugin_path.display());
+    } else if plugin_path.exists() {
+        fs::remove_file(&plugin_path)
+            .context("Failed to remove plugin file")?;
+        info!("✅ Plugin removed from {}", plugin_path.display());
+
+        // Zellij has no action to unload a single plugin, so a running instance
+        // stays alive until the session restarts
+        if env::var("ZELLIJ").is_ok() {
+            eprintln!("⚠️  The plugin is still loaded in this session. Restart Zellij to unload it.");
+        }
+    } else {
+        info!("Nothing to uninstall: no plugin at {}", plugin_path.display());
+    }
+
+    if purge {
+        let config_path = get_zellij_config_path(config_dir)?;
+        let content = fs::read_to_string(&config_path).unwrap_or_default();
+        match kdl_config::remove_plugin_block(&content) {
+            Some(updated) => {
+                if dry_run {
+                    write_zellij_config(&config_path, &content, &updated, dry_run)?;
+                } else if confirm(&format!("Remove the znotify plugin block from {}?", config_path.display()))? {
+                    write_zellij_config(&config_path, &content, &updated, dry_run)?;
+                    info!("✅ Plugin block removed from {}", config_path.display());
+                }
+            }
+            None => info!("No znotify plugin block in {}", config_path.display()),
+        }
+    }
+
+    Ok(())
+}
+
+// Everything `status` reports; also the stable shape of `status --json`
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct StatusReport {
+    plugin_installed: bool,
+    plugin_path: String,
+    hooks_installed: bool,
+    claude_settings_path: String,
+    claude_settings_error: Option<String>,  // Why settings.json couldn't be parsed; null when it could
+    in_zellij: bool,
+    pending_notifications: Option<usize>,  // Tabs showing a notification; null outside Zellij or without a reply
+    notifications: Vec<NotificationInfo>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct NotificationInfo {
+    name: String,
+    emoji: String,
+}
+
+fn status(config_dir: Option<&Path>, path: Option<&Path>, pipe_name: &str, cli_config: &CliConfig, json_output: bool) -> Result<()> {
+    let report = collect_status(config_dir, path, pipe_name, cli_config)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    info!("znotify status\n");
+
+    // Check plugin installation
+    info!("Plugin: {}", if report.plugin_installed {
+        format!("✅ Installed at {}", report.plugin_path)
+    } else {
+        "❌ Not installed (run: znotify install-plugin)".to_string()
+    });
+
+    // Check Claude hooks
+    info!("Claude hooks: {}", match (&report.claude_settings_error, report.hooks_installed) {
+        (Some(error), _) => format!("❌ {} is {} (fix it, or run: znotify claude install-hooks --force)",
+                                    report.claude_settings_path, error),
+        (None, true) => format!("✅ Installed at {}", report.claude_settings_path),
+        (None, false) => "❌ Not installed (run: znotify claude install-hooks)".to_string(),
+    });
+
+    // Check if in Zellij session
+    info!("Zellij session: {}", if report.in_zellij {
+        "✅ Running in Zellij"
+    } else {
+        "❌ Not in Zellij session"
+    });
+
+    info!("Pending notifications: {}", match report.pending_notifications {
+        Some(count) => count.to_string(),
+        None => "n/a".to_string(),
+    });
+
+    // Show available notifications
+    info!("\nAvailable notifications:");
+    for notification in &report.notifications {
+        info!("  {} {}", notification.emoji, notification.name);
+    }
+
+    Ok(())
+}
+
+fn collect_status(config_dir: Option<&Path>, path: Option<&Path>, pipe_name: &str, cli_config: &CliConfig) -> Result<StatusReport> {
+    let plugin_path = resolve_plugin_path(config_dir, path)?;
+    let claude_settings = get_claude_settings_path()?;
+
+    let (settings, claude_settings_error) = match read_claude_settings(&claude_settings) {
+        Ok(ClaudeSettings::Valid(settings)) => (Some(settings), None),
+        Ok(ClaudeSettings::Missing) => (None, None),
+        Ok(ClaudeSettings::Corrupt(e)) => (None, Some(format!("not valid JSON: {}", e))),
+        Err(e) => (None, Some(format!("{:#}", e))),
+    };
+    let hooks_installed = settings
+        .and_then(|s| s.get("hooks").cloned())
+        .map(|h| CLAUDE_HOOKS.iter().any(|(event, _, _)| {
+            h.get(*event)
+                .and_then(|g| g.as_array())
+                .is_some_and(|groups| hooks::hook_commands(groups).any(hooks::is_znotify_command))
+        }))
+        .unwrap_or(false);
+
+    // Only ask the plugin from inside Zellij; a missing or silent plugin just means no count
+    let in_zellij = env::var("ZELLIJ").is_ok();
+    let pending_notifications = if in_zellij {
+        pending_notifications(pipe_name).ok().map(|pending| pending.len())
+    } else {
+        None
+    };
+
+    Ok(StatusReport {
+        plugin_installed: plugin_path.exists(),
+        plugin_path: plugin_path.display().to_string(),
+        hooks_installed,
+        claude_settings_path: claude_settings.display().to_string(),
+        claude_settings_error,
+        in_zellij,
+        pending_notifications,
+        notifications: notifications(cli_config).into_iter()
+            .map(|(name, emoji)| NotificationInfo { name, emoji })
+            .collect(),
+    })
+}
+
+fn version(json_output: bool) -> Result<()> {
+    println!("{}", version_report(WASM_BYTES, json_output)?);
+    Ok(())
+}
+
+// What `version` prints for a CLI carrying `wasm`
+fn version_report(wasm: &[u8], json_output: bool) -> Result<String> {
+    let plugin = bundled_plugin_version(wasm);
+    if json_output {
+        let report = json!({
+            "cli": env!("CARGO_PKG_VERSION"),
+            "plugin": plugin,
+        });
+        return Ok(serde_json::to_string_pretty(&report)?);
+    }
+
+    Ok(format!("znotify {}\nembedded plugin {}", env!("CARGO_PKG_VERSION"), plugin.as_deref().unwrap_or("not bundled")))
+}
+
+// The version of the wasm this CLI carries: from its marker, else the plugin crate it was
+// built next to; None when nothing is embedded
+fn bundled_plugin_version(wasm: &[u8]) -> Option<String> {
+    if wasm.is_empty() {
+        return None;
+    }
+    Some(wasm_version(wasm).unwrap_or_else(|| PLUGIN_VERSION.to_string()))
+}
+
+// One line of `doctor` output. Critical failures make the command exit nonzero.
+struct Check {
+    name: &'static str,
+    ok: bool,
+    critical: bool,
+    detail: String,
+    hint: String,
+}
+
+fn doctor(config_dir: Option<&Path>) -> Result<()> {
+    let checks = vec![
+        check_plugin_file(config_dir)?,
+        check_plugin_block(config_dir)?,
+        check_zellij_on_path(),
+        check_claude_hooks()?,
+        check_pane_id(),
+    ];
+
+    println!("znotify doctor\n");
+    for check in &checks {
+        let mark = match (check.ok, check.critical) {
+            (true, _) => "✅",
+            (false, true) => "❌",
+            (false, false) => "⚠️ ",
+        };
+        println!("{} {}: {}", mark, check.name, check.detail);
+        if !check.ok {
+            println!("   → {}", check.hint);
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.critical && !c.ok).count();
+    if failed > 0 {
+        bail!("{} critical check(s) failed", failed);
+    }
+    Ok(())
+}
+
+fn check_plugin_file(config_dir: Option<&Path>) -> Result<Check> {
+    let plugin_path = get_plugin_path(config_dir)?;
+    let (ok, detail) = match fs::metadata(&plugin_path) {
+        Ok(meta) if meta.len() == 0 => (false, format!("{} is empty", plugin_path.display())),
+        Ok(_) => match fs::File::open(&plugin_path) {
+            Ok(_) => (true, format!("installed at {}", plugin_path.display())),
+            Err(e) => (false, format!("{} is not readable: {}", plugin_path.display(), e)),
+        },
+        Err(_) => (false, format!("not found at {}", plugin_path.display())),
+    };
+    Ok(Check {
+        name: "Plugin",
+        ok,
+        critical: true,
+        detail,
+        hint: "run: znotify install-plugin".to_string(),
+    })
+}
+
+fn check_plugin_block(config_dir: Option<&Path>) -> Result<Check> {
+    let config_path = get_zellij_config_path(config_dir)?;
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+    let ok = kdl_config::find_plugin_block(&content).is_some();
+    Ok(Check {
+        name: "Zellij config",
+        ok,
+        critical: true,
+        detail: if ok {
+            format!("plugin block found in {}", config_path.display())
+        } else {
+            format!("no znotify plugin block in {}", config_path.display())
+        },
+        hint: "add the block printed by: znotify config".to_string(),
+    })
+}
+
+fn check_zellij_on_path() -> Check {
+    let found = find_in_path("zellij");
+    Check {
+        name: "zellij binary",
+        ok: found.is_some(),
+        critical: true,
+        detail: match &found {
+            Some(path) => format!("found at {}", path.display()),
+            None => "not found on PATH".to_string(),
+        },
+        hint: "install Zellij or add it to PATH".to_string(),
+    }
+}
+
+fn check_claude_hooks() -> Result<Check> {
+    let claude_settings = get_claude_settings_path()?;
+    let settings = match read_claude_settings(&claude_settings) {
+        Ok(ClaudeSettings::Valid(settings)) => Some(settings),
+        Ok(ClaudeSettings::Missing) | Err(_) => None,
+        Ok(ClaudeSettings::Corrupt(e)) => return Ok(Check {
+            name: "Claude hooks",
+            ok: false,
+            critical: false,
+            detail: format!("{} is not valid JSON: {}", claude_settings.display(), e),
+            hint: "fix it, or run: znotify claude install-hooks --force".to_string(),
+        }),
+    };
+
+    // Every hook command that calls znotify, across all events
+    let commands: Vec<String> = settings.as_ref()
+        .and_then(|s| s.get("hooks"))
+        .and_then(|h| h.as_object())
+        .map(|hooks| hooks.values()
+            .filter_map(|groups| groups.as_array())
+            .flatten()
+            .filter_map(|group| group.get("hooks").and_then(|h| h.as_array()))
+            .flatten()
+            .filter_map(|hook| hook.get("command").and_then(|c| c.as_str()))
+            .filter(|command| command.contains("znotify"))
+            .map(|command| command.to_string())
+            .collect())
+        .unwrap_or_default();
+
+    let mut check = Check {
+        name: "Claude hooks",
+        ok: false,
+        critical: false,
+        detail: format!("no znotify hooks in {}", claude_settings.display()),
+        hint: "run: znotify claude install-hooks".to_string(),
+    };
+    if commands.is_empty() {
+        return Ok(check);
+    }
+
+    // The first word of each command must resolve to an executable
+    let mut unresolved: Vec<&str> = commands.iter()
+        .filter_map(|command| command.split_whitespace().next())
+        .filter(|program| resolve_program(program).is_none())
+        .collect();
+    unresolved.sort();
+    unresolved.dedup();
+
+    if unresolved.is_empty() {
+        check.ok = true;
+        check.detail = format!("{} hook(s) installed in {}", commands.len(), claude_settings.display());
+    } else {
+        check.detail = format!("hook command not found: {}", unresolved.join(", "));
+        check.hint = "put znotify on PATH (e.g. cargo install --path cli)".to_string();
+    }
+    Ok(check)
+}
+
+fn check_pane_id() -> Check {
+    let pane_id = env::var("ZELLIJ_PANE_ID").ok();
+    Check {
+        name: "ZELLIJ_PANE_ID",
+        ok: pane_id.is_some(),
+        critical: false,
+        detail: match &pane_id {
+            Some(id) => format!("set to {}", id),
+            None => "not set".to_string(),
+        },
+        hint: "run znotify from a pane inside Zellij".to_string(),
+    }
+}
+
+// A program as a hook would run it: a path is checked directly, a bare name is looked up on PATH
+fn resolve_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return path.is_file().then_some(path);
+    }
+    find_in_path(program)
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+fn logs(config_dir: Option<&Path>, file: Option<&Path>, lines: Option<usize>, follow: bool) -> Result<()> {
+    let path = match file {
+        Some(file) => file.to_path_buf(),
+        None => plugin_log_file(config_dir)?,
+    };
+    let read = write_log(&path, lines, &mut io::stdout().lock())?;
+
+    if follow {
+        follow_file(&path, read)?;
+    }
+    Ok(())
+}
+
+// Write the last `lines` lines of the log (all of it for None), returning how many bytes were read
+fn write_log(path: &Path, lines: Option<usize>, out: &mut impl Write) -> Result<u64> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => bail!(
+            "No log file at {}. The plugin creates it on load once log_file is set; \
+             check the plugin block in config.kdl and restart Zellij",
+            path.display()
+        ),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    let text = String::from_utf8_lossy(&content);
+    let all: Vec<&str> = text.lines().collect();
+    let skip = lines.map(|n| all.len().saturating_sub(n)).unwrap_or(0);
+    for line in &all[skip..] {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(content.len() as u64)
+}
+
+// The host path of the log_file set in our plugin block
+fn plugin_log_file(config_dir: Option<&Path>) -> Result<PathBuf> {
+    let config_path = get_zellij_config_path(config_dir)?;
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+    let Some(log_file) = kdl_config::read_option(&content, "log_file").filter(|p| !p.is_empty()) else {
+        bail!(
+            "No log_file set in the znotify plugin block in {}. Add one to log to a file:\n    \
+             log_level \"info\"\n    log_file \"/tmp/zellij-notify.log\"\n\
+             or pass --file",
+            config_path.display()
+        );
+    };
+    Ok(host_log_path(Path::new(&log_file)))
+}
+
+// Zellij mounts its own temp dir (<tmp>/zellij-<uid>) at the plugin's /tmp, so a
+// log_file under /tmp ends up in there on the host; other paths are used as written
+fn host_log_path(log_file: &Path) -> PathBuf {
+    let Ok(rest) = log_file.strip_prefix("/tmp") else {
+        return log_file.to_path_buf();
+    };
+    let mounted = fs::read_dir(env::temp_dir()).into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("zellij-"))
+        .map(|entry| entry.path().join(rest))
+        .find(|path| path.is_file());
+    mounted.unwrap_or_else(|| log_file.to_path_buf())
+}
+
+// Print whatever gets appended to `path` past `offset`, until interrupted
+fn follow_file(path: &Path, mut offset: u64) -> Result<()> {
+    let mut stdout = io::stdout();
+    loop {
+        thread::sleep(FOLLOW_INTERVAL);
+        let len = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        // Truncated or recreated: start over from the top
+        if len < offset {
+            offset = 0;
+        }
+        if len == offset {
+            continue;
+        }
+
+        let mut file = fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut appended = Vec::new();
+        file.read_to_end(&mut appended)?;
+        offset += appended.len() as u64;
+        stdout.write_all(&appended)?;
+        stdout.flush()?;
+    }
+}
+
+fn config(config_dir: Option<&Path>, cli_config: &CliConfig) -> Result<()> {
+    println!("Add this to your Zellij config ({}):\n", get_zellij_config_path(config_dir)?.display());
+    println!("{}", config_template(config_dir, cli_config)?);
+    Ok(())
+}
+
+// One effective value for `config show`, and where it came from
+#[derive(Serialize)]
+struct Effective {
+    value: Value,
+    source: String,
+}
+
+fn effective(value: impl Into<Value>, source: impl Into<String>) -> Effective {
+    Effective { value: value.into(), source: source.into() }
+}
+
+fn config_show(config_dir: Option<&Path>, pipe_name: &str, cli_config: &CliConfig, json_output: bool) -> Result<()> {
+    let cli_config_path = get_cli_config_path()?;
+    let file_keys = cli_config::file_keys(&cli_config_path);
+    let file = "config.toml";
+
+    // Paths, each with the flag or variable that decided it
+    let config_home = if env_dir("XDG_CONFIG_HOME").is_some() { "$XDG_CONFIG_HOME" } else { "default" };
+    let zellij_source = if config_dir.is_some() {
+        "--config-dir"
+    } else if env_dir("ZELLIJ_CONFIG_DIR").is_some() {
+        "$ZELLIJ_CONFIG_DIR"
+    } else {
+        config_home
+    };
+    let claude_source = if env_dir("CLAUDE_CONFIG_DIR").is_some() { "$CLAUDE_CONFIG_DIR" } else { "default" };
+    let history_source = if env_dir("XDG_STATE_HOME").is_some() { "$XDG_STATE_HOME" } else { "default" };
+    let path = |path: PathBuf, source: &str| effective(path.display().to_string(), source);
+    let paths = vec![
+        ("cli_config", path(cli_config_path.clone(), config_home)),
+        ("zellij_config", path(get_zellij_config_path(config_dir)?, zellij_source)),
+        ("plugin", path(get_plugin_path(config_dir)?, zellij_source)),
+        ("claude_settings", path(get_claude_settings_path()?, claude_source)),
+        ("history", path(get_history_path()?, history_source)),
+    ];
+
+    // A setting from config.toml, with an env var that can also turn it on
+    let from_file = |key: &str| if file_keys.contains(key) { file } else { "default" };
+    let flag_source = |key: &str, var: &str| if env_flag(var) { format!("${}", var) } else { from_file(key).to_string() };
+    let number = |var: &str, default: u64| match env::var(var).ok().and_then(|v| v.parse::<u64>().ok()) {
+        Some(value) => effective(value, format!("${}", var)),
+        None => effective(default, "default"),
+    };
+    let pipe_source = match env::var("ZNOTIFY_PIPE_NAME") {
+        Ok(value) if value == pipe_name => "$ZNOTIFY_PIPE_NAME",
+        _ if pipe_name == "notify" => "default",
+        _ => "--pipe-name",
+    };
+    let settings = vec![
+        ("pipe_name", effective(pipe_name, pipe_source)),
+        ("desktop_fallback", effective(cli_config.desktop_fallback || env_flag("ZNOTIFY_DESKTOP_FALLBACK"),
+            flag_source("desktop_fallback", "ZNOTIFY_DESKTOP_FALLBACK"))),
+        ("bell", effective(json!(cli_config.bell), from_file("bell"))),
+        ("bell_always", effective(env_flag("ZNOTIFY_BELL"),
+            if env_flag("ZNOTIFY_BELL") { "$ZNOTIFY_BELL" } else { "default" })),
+        ("history", effective(cli_config.history, from_file("history"))),
+        ("auto_install", effective(cli_config.auto_install, from_file("auto_install"))),
+        ("attempts", number("ZNOTIFY_ATTEMPTS", DEFAULT_ATTEMPTS.into())),
+        ("retry_delay_ms", number("ZNOTIFY_RETRY_DELAY_MS", DEFAULT_RETRY_DELAY_MS)),
+    ];
+
+    // The notify table, and what the plugin block in config.kdl says for the same names
+    let kdl = fs::read_to_string(get_zellij_config_path(config_dir)?).unwrap_or_default();
+    let plugin_presets = kdl_config::read_presets(&kdl).ok().flatten().unwrap_or_default();
+    let notifications: Vec<Value> = notifications(cli_config).into_iter()
+        .map(|(name, emoji)| {
+            let builtin = NOTIFY_CONFIG.iter().find(|(n, _)| *n == name).map(|(_, e)| *e);
+            let source = match (cli_config.presets.contains_key(&name), builtin) {
+                (true, Some(builtin)) if builtin != emoji => format!("{}, overrides {}", file, builtin),
+                (true, _) => file.to_string(),
+                (false, _) => "built-in".to_string(),
+            };
+            let plugin = plugin_presets.get(&name).and_then(|p| p.get("emoji")).cloned();
+            json!({"name": name, "emoji": emoji, "source": source, "plugin_emoji": plugin})
+        })
+        .collect();
+
+    if json_output {
+        let section = |entries: Vec<(&str, Effective)>| -> Result<Value> {
+            let map = entries.into_iter()
+                .map(|(key, value)| Ok((key.to_string(), serde_json::to_value(value)?)))
+                .collect::<Result<serde_json::Map<_, _>>>()?;
+            Ok(Value::Object(map))
+        };
+        let report = json!({
+            "paths": section(paths)?,
+            "settings": section(settings)?,
+            "notifications": notifications,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let text = |value: &Value| match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) if items.is_empty() => "(none)".to_string(),
+        Value::Array(items) => items.iter().map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+            .collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    };
+    for (title, entries) in [("Paths", &paths), ("Settings", &settings)] {
+        println!("{}:", title);
+        for (key, entry) in entries {
+            println!("   {:<18} {}  ({})", key, text(&entry.value), entry.source);
+        }
+        println!();
+    }
+    println!("Notifications:");
+    for notification in &notifications {
+        let plugin = match notification["plugin_emoji"].as_str() {
+            Some(emoji) if Some(emoji) != notification["emoji"].as_str() => format!(", config.kdl has {}", emoji),
+            Some(_) => String::new(),
+            None if plugin_presets.is_empty() => String::new(),
+            None => ", missing from config.kdl".to_string(),
+        };
+        println!("   {:<18} {}  ({}{})", text(&notification["name"]), text(&notification["emoji"]),
+                 text(&notification["source"]), plugin);
+    }
+    Ok(())
+}
+
+fn install_config(config_dir: Option<&Path>, cli_config: &CliConfig, dry_run: bool) -> Result<()> {
+    let config_path = get_zellij_config_path(config_dir)?;
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+
+    if kdl_config::find_plugin_block(&content).is_some() {
+        info!("✅ {} already has a znotify plugin block; leaving it as is", config_path.display());
+        return Ok(());
+    }
+
+    // Zellij loads plugins at startup from `load_plugins`, where entries are bare locations
+    let entry = config_template(config_dir, cli_config)?.replacen("plugin location=", "", 1);
+    let updated = kdl_config::add_to_load_plugins(&content, &entry);
+    write_zellij_config(&config_path, &content, &updated, dry_run)?;
+    if dry_run {
+        return Ok(());
+    }
+
+    info!("✅ Added the znotify plugin block to {}", config_path.display());
+    info!("   Restart Zellij to load it");
+    Ok(())
+}
+
+// install-plugin, install-config and claude install-hooks in one go. Each step is
+// already idempotent; a failed step doesn't stop the others, so the summary is complete
+fn init(config_dir: Option<&Path>, cli_config: &CliConfig, dry_run: bool) -> Result<()> {
+    let steps = [
+        ("Install the plugin", install_plugin(config_dir, None, dry_run)),
+        ("Add the plugin block to config.kdl", install_config(config_dir, cli_config, dry_run)),
+        ("Install Claude hooks", claude_install_hooks(&[], true, false, dry_run)
+            .and_then(|_| sync_presets(config_dir, cli_config, dry_run))),
+    ];
+
+    info!("");
+    info!("Summary:");
+    let mut failed = 0;
+    for (step, result) in &steps {
+        match result {
+            Ok(()) => info!("   ✅ {}", step),
+            Err(e) => {
+                eprintln!("   ❌ {}: {:#}", step, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{} of {} setup steps failed; fix the errors above and run znotify init again",
+              failed, steps.len());
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    info!("");
+    info!("Next steps:");
+    info!("   1. Restart Zellij so it loads the plugin block");
+    info!("   2. Grant the plugin's permission prompt");
+    info!("   3. Run `znotify test` in a Zellij pane to check the whole setup");
+    Ok(())
+}
+
+// Bring the emojis in config.kdl's presets in line with the notification table,
+// keeping any other preset fields and presets the table doesn't know
+fn sync_presets(config_dir: Option<&Path>, cli_config: &CliConfig, dry_run: bool) -> Result<()> {
+    let config_path = get_zellij_config_path(config_dir)?;
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+    let Some(mut presets) = kdl_config::read_presets(&content)? else {
+        return Ok(());
+    };
+
+    let mut changed = Vec::new();
+    for (name, emoji) in notifications(cli_config) {
+        match presets.get_mut(&name).and_then(|p| p.as_object_mut()) {
+            Some(preset) if preset.get("emoji").and_then(|e| e.as_str()) == Some(emoji.as_str()) => continue,
+            Some(preset) => {
+                preset.insert("emoji".to_string(), json!(emoji));
+            }
+            None => {
+                presets.insert(name.clone(), json!({"emoji": emoji}));
+            }
+        }
+        changed.push(name);
+    }
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    let updated = kdl_config::write_presets(&content, &presets)
+        .context("znotify plugin block disappeared while editing")?;
+    write_zellij_config(&config_path, &content, &updated, dry_run)?;
+    if dry_run {
+        return Ok(());
+    }
+
+    info!("✅ Synced presets in {}: {}", config_path.display(), changed.join(", "));
+    info!("   Reload the plugin for them to take effect: znotify install-plugin");
+    Ok(())
+}
+
+fn preset_list(config_dir: Option<&Path>) -> Result<()> {
+    let config_path = get_zellij_config_path(config_dir)?;
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+
+    match kdl_config::read_presets(&content)? {
+        Some(presets) if !presets.is_empty() => {
+            println!("Presets in {}:", config_path.display());
+            for (name, preset) in &presets {
+                let emoji = preset.get("emoji").and_then(|e| e.as_str()).unwrap_or("?");
+                println!("  {} {}", emoji, name);
+            }
+        }
+        Some(_) => println!("No presets configured in {}", config_path.display()),
+        None => println!("No znotify plugin block in {} (run: znotify config)", config_path.display()),
+    }
+    Ok(())
+}
+
+fn preset_add(config_dir: Option<&Path>, cli_config: &CliConfig, name: &str, emoji: &str, dry_run: bool) -> Result<()> {
+    let config_path = get_zellij_config_path(config_dir)?;
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+
+    let Some(mut presets) = kdl_config::read_presets(&content)? else {
+        if !confirm(&format!("No znotify plugin block in {}. Create one?", config_path.display()))? {
+            return Ok(());
+        }
+        // Start from the template so the new block carries the default presets too
+        let mut updated = content.clone();
+        if !updated.is_empty() && !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+        updated.push_str(&config_template(config_dir, cli_config)?);
+        if dry_run {
+            // Nothing was written, so preview the edit against the in-memory config
+            // instead of recursing into a file that still has no block
+            let mut presets = kdl_config::read_presets(&updated)?.unwrap_or_default();
+            presets.insert(name.to_string(), json!({"emoji": emoji}));
+            let updated = kdl_config::write_presets(&updated, &presets)
+                .context("znotify plugin block disappeared while editing")?;
+            return write_zellij_config(&config_path, &content, &updated, dry_run);
+        }
+        write_zellij_config(&config_path, &content, &updated, dry_run)?;
+        return preset_add(config_dir, cli_config, name, emoji, dry_run);
+    };
+
+    // Keep any other fields (like label) on an existing preset
+    match presets.get_mut(name).and_then(|p| p.as_object_mut()) {
+        Some(preset) => {
+            preset.insert("emoji".to_string(), json!(emoji));
+        }
+        None => {
+            presets.insert(name.to_string(), json!({"emoji": emoji}));
+        }
+    }
+
+    let updated = kdl_config::write_presets(&content, &presets)
+        .context("znotify plugin block disappeared while editing")?;
+    write_zellij_config(&config_path, &content, &updated, dry_run)?;
+    if dry_run {
+        return Ok(());
+    }
+
+    info!("✅ Preset '{}' set to {} in {}", name, emoji, config_path.display());
+    Ok(())
+}
+
+fn preset_remove(config_dir: Option<&Path>, name: &str, dry_run: bool) -> Result<()> {
+    let config_path = get_zellij_config_path(config_dir)?;
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+
+    let Some(mut presets) = kdl_config::read_presets(&content)? else {
+        info!("No znotify plugin block in {}", config_path.display());
+        return Ok(());
+    };
+
+    if presets.shift_remove(name).is_none() {
+        info!("No preset named '{}'", name);
+        return Ok(());
+    }
+
+    let updated = kdl_config::write_presets(&content, &presets)
+        .context("znotify plugin block disappeared while editing")?;
+    write_zellij_config(&config_path, &content, &updated, dry_run)?;
+    if dry_run {
+        return Ok(());
+    }
+
+    info!("✅ Preset '{}' removed from {}", name, config_path.display());
+    Ok(())
+}
+
+// Run presets JSON through the plugin's own parser and checks, one line per preset in file order
+fn validate_presets(input: &str) -> Result<()> {
+    let json = if input == "-" {
+        let mut json = String::new();
+        io::stdin().read_to_string(&mut json).context("Failed to read presets from stdin")?;
+        json
+    } else {
+        input.to_string()
+    };
+
+    // The plugin parses the object in one go, so a single bad entry costs every preset
+    let parsed = preset::parse(&json);
+    let entries: serde_json::Map<String, Value> = serde_json::from_str(&json)
+        .map_err(|e| anyhow!("Presets are not a JSON object: {}; the plugin would load none of them", e))?;
+
+    if entries.is_empty() {
+        info!("No presets to check");
+    }
+
+    let mut skipped = 0;
+    for (name, value) in entries {
+        let preset = match serde_json::from_value::<PresetConfig>(value) {
+            Ok(preset) => preset,
+            Err(e) => {
+                skipped += 1;
+                println!("❌ {}: {}", name, e);
+                continue;
+            }
+        };
+        let problems = preset::problems(&preset);
+        let details = problems.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ");
+        if problems.iter().any(Problem::skips_preset) {
+            skipped += 1;
+            println!("❌ {}: {}, the plugin skips it", name, details);
+        } else if problems.is_empty() {
+            println!("✅ {}: {}", name, preset.emoji);
+        } else {
+            println!("⚠️  {}: {} ({}, ignored)", name, preset.emoji, details);
+        }
+    }
+
+    if let Err(e) = parsed {
+        bail!("The plugin would load none of these presets: {}", e);
+    }
+    if skipped > 0 {
+        bail!("{} preset(s) would be skipped", skipped);
+    }
+    Ok(())
+}
+
+// Back up and rewrite config.kdl, creating it (and its directory) if needed
+fn mute(config_dir: Option<&Path>, tab: &str, dry_run: bool) -> Result<()> {
+    let tab = tab.trim();
+    if tab.is_empty() || tab.contains(',') {
+        bail!("Tab must be a position or a name without commas");
+    }
+
+    let config_path = get_zellij_config_path(config_dir)?;
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+    if kdl_config::find_plugin_block(&content).is_none() {
+        bail!("No znotify plugin block in {}. Add one first, see: znotify config", config_path.display());
+    }
+
+    let mut muted: Vec<String> = kdl_config::read_option(&content, "mute_tabs")
+        .map(|list| list.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+        .unwrap_or_default();
+    if muted.iter().any(|t| t == tab) {
+        info!("✅ '{}' is already muted in {}", tab, config_path.display());
+        return Ok(());
+    }
+    muted.push(tab.to_string());
+
+    let updated = kdl_config::write_option(&content, "mute_tabs", &muted.join(", "))
+        .context("znotify plugin block disappeared while editing")?;
+    write_zellij_config(&config_path, &content, &updated, dry_run)?;
+    if dry_run {
+        return Ok(());
+    }
+
+    info!("✅ Muted '{}' in {}", tab, config_path.display());
+    info!("   Reload the plugin for it to take effect: znotify install-plugin");
+    Ok(())
+}
+
+fn write_zellij_config(path: &Path, original: &str, updated: &str, dry_run: bool) -> Result<()> {
+    if !original.is_empty() {
+        backup_file(path, dry_run)?;
+    }
+    if dry_run {
+        println!("Would write {}:", path.display());
+        println!("{}", updated);
+        return Ok(());
+    }
+    fs::create_dir_all(path.parent().unwrap())
+        .context("Failed to create Zellij config directory")?;
+    fs::write(path, updated)
+        .context("Failed to write Zellij config")?;
+    Ok(())
+}
+
+// Ask a yes/no question on stdin; anything but y/yes is a no
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn completions(shell: Shell) -> Result<()> {
+    let mut cmd = Cli::command();
+    let name = cmd.get_name().to_string();
+    clap_complete::generate(shell, &mut cmd, name, &mut std::io::stdout());
+    Ok(())
+}
+
+// ZELLIJ_CONFIG_TEMPLATE with its plugin location pointing at where install-plugin writes,
+// and its presets matching the CLI's notification table
+fn config_template(config_dir: Option<&Path>, cli_config: &CliConfig) -> Result<String> {
+    // The same table `notify` uses, so hooks and plugin always agree on the emojis
+    let presets = notifications(cli_config).into_iter()
+        .map(|(name, emoji)| (name, json!({"emoji": emoji})))
+        .collect();
+    let template = kdl_config::write_presets(ZELLIJ_CONFIG_TEMPLATE, &presets)
+        .context("config template has no plugin block")?;
+
+    let plugin_path = get_plugin_path(config_dir)?;
+    let default_path = home_dir()?.join(".config").join("zellij").join("plugins").join("zellij-notify.wasm");
+    if plugin_path == default_path {
+        return Ok(template);
+    }
+    Ok(template.replace(
+        "file:~/.config/zellij/plugins/zellij-notify.wasm",
+        &format!("file:{}", plugin_path.display()),
+    ))
+}
+
+// Notification name -> emoji: NOTIFY_CONFIG with config.toml presets merged over it
+fn notifications(cli_config: &CliConfig) -> Vec<(String, String)> {
+    cli_config::merge_presets(NOTIFY_CONFIG, &cli_config.presets)
+}
+
+fn get_cli_config_path() -> Result<PathBuf> {
+    Ok(config_home()?.join("znotify").join("config.toml"))
+}
+
+fn get_history_path() -> Result<PathBuf> {
+    Ok(state_home()?.join("znotify").join("history.jsonl"))
+}
+
+// $HOME, then Windows' %USERPROFILE% and %HOMEDRIVE%%HOMEPATH%, so hook management
+// works on native Windows where only Zellij itself is unavailable
+fn home_dir() -> Result<PathBuf> {
+    if let Some(home) = env_dir("HOME").or_else(|| env_dir("USERPROFILE")) {
+        return Ok(home);
+    }
+    if let (Some(drive), Some(path)) = (env::var_os("HOMEDRIVE"), env::var_os("HOMEPATH")) {
+        let mut home = drive;
+        home.push(path);
+        let home = PathBuf::from(home);
+        if home.is_absolute() {
+            return Ok(home);
+        }
+    }
+    bail!("Could not find your home directory: set HOME (or USERPROFILE on Windows)")
+}
+
+// A directory from an env var, ignoring empty and relative values as the XDG spec asks
+fn env_dir(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+}
+
+// $XDG_CONFIG_HOME, falling back to ~/.config
+fn config_home() -> Result<PathBuf> {
+    match env_dir("XDG_CONFIG_HOME") {
+        Some(dir) => Ok(dir),
+        None => Ok(home_dir()?.join(".config")),
+    }
+}
+
+// $XDG_STATE_HOME, falling back to ~/.local/state
+fn state_home() -> Result<PathBuf> {
+    match env_dir("XDG_STATE_HOME") {
+        Some(dir) => Ok(dir),
+        None => Ok(home_dir()?.join(".local").join("state")),
+    }
+}
+
+// --config-dir, then $ZELLIJ_CONFIG_DIR, then <config home>/zellij
+fn get_zellij_config_dir(config_dir: Option<&Path>) -> Result<PathBuf> {
+    if let Some(dir) = config_dir {
+        return Ok(dir.to_path_buf());
+    }
+    match env_dir("ZELLIJ_CONFIG_DIR") {
+        Some(dir) => Ok(dir),
+        None => Ok(config_home()?.join("zellij")),
+    }
+}
+
+// $CLAUDE_CONFIG_DIR/settings.json, falling back to ~/.claude/settings.json
+fn get_claude_settings_path() -> Result<PathBuf> {
+    let claude_dir = match env_dir("CLAUDE_CONFIG_DIR") {
+        Some(dir) => dir,
+        None => home_dir()?.join(".claude"),
+    };
+    Ok(claude_dir.join("settings.json"))
+}
+
+fn get_zellij_config_path(config_dir: Option<&Path>) -> Result<PathBuf> {
+    Ok(get_zellij_config_dir(config_dir)?.join("config.kdl"))
+}
+
+fn get_plugin_path(config_dir: Option<&Path>) -> Result<PathBuf> {
+    Ok(get_zellij_config_dir(config_dir)?
+        .join("plugins")
+        .join("zellij-notify.wasm"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::sync::Mutex;
+
+    // Tests that read or move the CLI's paths through the environment take turns
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    // Run `f` with each of `vars` set, or unset for None, restoring them afterwards
+    fn with_env<T>(vars: &[(&str, Option<&OsStr>)], f: impl FnOnce() -> T) -> T {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let saved: Vec<_> = vars.iter().map(|(name, _)| (*name, env::var_os(name))).collect();
+        for (name, value) in vars {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+
+        let result = f();
+        for (name, value) in saved {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        result
+    }
+
+    // Run `f` outside Zellij with HOME at a fresh temp dir and every other path override
+    // unset, so ~/.claude, ~/.config and ~/.local/state all land inside it
+    fn in_home<T>(f: impl FnOnce(&Path) -> T) -> T {
+        let home = tempfile::tempdir().unwrap();
+        let vars = [
+            ("ZELLIJ", None),
+            ("HOME", Some(home.path().as_os_str())),
+            ("USERPROFILE", None),
+            ("XDG_CONFIG_HOME", None),
+            ("XDG_STATE_HOME", None),
+            ("CLAUDE_CONFIG_DIR", None),
+            ("ZELLIJ_CONFIG_DIR", None),
+        ];
+        with_env(&vars, || f(home.path()))
+    }
+
+    // Run `f` as if from pane 4 of tab "build" in session "main"
+    fn in_zellij<T>(f: impl FnOnce() -> T) -> T {
+        let vars = [
+            ("ZELLIJ", Some(OsStr::new("0"))),
+            ("ZELLIJ_PANE_ID", Some(OsStr::new("4"))),
+            ("ZELLIJ_SESSION_NAME", Some(OsStr::new("main"))),
+            ("ZELLIJ_TAB_NAME", Some(OsStr::new("build"))),
+        ];
+        with_env(&vars, f)
+    }
+
+    // The words of a command after the program name
+    fn words(cmd: &Command) -> Vec<String> {
+        cmd.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect()
+    }
+
+    // NotifyArgs as clap parses them from a `znotify notify ...` command line
+    fn notify_args(line: &[&str]) -> NotifyArgs {
+        let cli = Cli::try_parse_from(["znotify", "notify"].iter().chain(line)).unwrap();
+        match cli.command {
+            Commands::Notify(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    // Names of the entries in `dir` that start with `prefix`
+    fn files_starting_with(dir: &Path, prefix: &str) -> Vec<String> {
+        fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(prefix))
+            .collect()
+    }
+
+    #[test]
+    fn bash_completions_generate() {
+        Cli::command().debug_assert();
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Bash, &mut Cli::command(), "znotify", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("_znotify()"));
+        assert!(script.contains("install-plugin"));
+    }
+
+    #[test]
+    fn install_hooks_backs_up_existing_settings() {
+        in_home(|home| {
+            let claude = home.join(".claude");
+            fs::create_dir_all(&claude).unwrap();
+            fs::write(claude.join("settings.json"), r#"{"model": "opus"}"#).unwrap();
+
+            claude_install_hooks(&[], true, false, false).unwrap();
+            let backups = files_starting_with(&claude, "settings.json.bak.");
+            assert_eq!(backups.len(), 1);
+            assert_eq!(fs::read_to_string(claude.join(&backups[0])).unwrap(), r#"{"model": "opus"}"#);
+        });
+    }
+
+    #[test]
+    fn install_hooks_without_settings_makes_no_backup() {
+        in_home(|home| {
+            claude_install_hooks(&[], true, false, false).unwrap();
+            let claude = home.join(".claude");
+            assert!(claude.join("settings.json").exists());
+            assert!(files_starting_with(&claude, "settings.json.bak.").is_empty());
+        });
+    }
+
+    #[test]
+    fn uninstall_plugin_removes_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = dir.path().join("plugins/zellij-notify.wasm");
+        fs::create_dir_all(plugin.parent().unwrap()).unwrap();
+        fs::write(&plugin, b"\0asm").unwrap();
+
+        uninstall_plugin(Some(dir.path()), false, true).unwrap();
+        assert!(plugin.exists());
+        uninstall_plugin(Some(dir.path()), false, false).unwrap();
+        assert!(!plugin.exists());
+        // Nothing left to remove is not an error
+        uninstall_plugin(Some(dir.path()), false, false).unwrap();
+    }
+
+    #[test]
+    fn notify_with_emoji_sends_it_as_a_raw_emoji_arg() {
+        let args = notify_args(&["deploy", "--emoji", "🦀"]);
+        let extra_args = notify_pipe_args(&args, args.emoji.as_deref());
+        assert_eq!(extra_args, vec![("emoji", "🦀".to_string())]);
+
+        let cmd = in_zellij(|| build_pipe_command("notify", "deploy", None, &extra_args).unwrap());
+        assert_eq!(cmd.get_program(), "zellij");
+        assert_eq!(words(&cmd), [
+            "pipe", "-n", "notify", "-a", "pane_id=4", "-a", "session_name=main", "-a", "tab_name=build",
+            "-a", "emoji=🦀", "deploy",
+        ]);
+    }
+
+    #[test]
+    fn status_json_reads_back_into_the_report() {
+        let report = in_home(|home| collect_status(Some(&home.join("zellij")), None, "notify", &CliConfig::default()).unwrap());
+        assert!(!report.plugin_installed);
+        assert!(!report.in_zellij);
+        assert_eq!(report.notifications[0], NotificationInfo { name: "notification".to_string(), emoji: "⚡".to_string() });
+
+        let json = serde_json::to_string_pretty(&report).unwrap();
+        assert_eq!(serde_json::from_str::<StatusReport>(&json).unwrap(), report);
+    }
+
+    #[test]
+    fn dry_run_hook_installs_write_nothing() {
+        in_home(|home| {
+            claude_install_hooks(&[], true, false, true).unwrap();
+            assert!(!home.join(".claude").exists());
+
+            let settings = home.join("tool/settings.json");
+            hooks_install("stop", &CliConfig::default(), &settings, "/hooks/on_stop", None, true, true).unwrap();
+            assert!(!settings.exists());
+
+            // An existing file isn't touched either, nor backed up
+            let claude = home.join(".claude");
+            fs::create_dir_all(&claude).unwrap();
+            fs::write(claude.join("settings.json"), "{}").unwrap();
+            claude_install_hooks(&[], true, false, true).unwrap();
+            assert_eq!(fs::read_to_string(claude.join("settings.json")).unwrap(), "{}");
+            assert!(files_starting_with(&claude, "settings.json.bak.").is_empty());
+        });
+    }
+
+    #[test]
+    fn notify_all_sets_the_broadcast_args() {
+        let args = notify_args(&["stop", "--all"]);
+        assert_eq!(notify_pipe_args(&args, None), vec![("target", "all".to_string())]);
+        let args = notify_args(&["stop", "--all", "--skip-active"]);
+        assert_eq!(notify_pipe_args(&args, None), vec![("target", "all".to_string()), ("skip_active", "true".to_string())]);
+    }
+
+    #[test]
+    fn config_paths_follow_the_env_vars() {
+        in_home(|home| {
+            assert_eq!(get_zellij_config_dir(None).unwrap(), home.join(".config/zellij"));
+            assert_eq!(get_claude_settings_path().unwrap(), home.join(".claude/settings.json"));
+
+            // in_home restores these afterwards
+            env::set_var("XDG_CONFIG_HOME", "/xdg");
+            env::set_var("CLAUDE_CONFIG_DIR", "/claude");
+            assert_eq!(get_zellij_config_dir(None).unwrap(), Path::new("/xdg/zellij"));
+            assert_eq!(get_cli_config_path().unwrap(), Path::new("/xdg/znotify/config.toml"));
+            assert_eq!(get_claude_settings_path().unwrap(), Path::new("/claude/settings.json"));
+
+            // ZELLIJ_CONFIG_DIR beats XDG_CONFIG_HOME, and --config-dir beats both
+            env::set_var("ZELLIJ_CONFIG_DIR", "/zellij");
+            assert_eq!(get_zellij_config_dir(None).unwrap(), Path::new("/zellij"));
+            assert_eq!(get_zellij_config_dir(Some(Path::new("/flag"))).unwrap(), Path::new("/flag"));
+
+            // Relative values are ignored, as the XDG spec asks
+            env::set_var("XDG_CONFIG_HOME", "relative");
+            env::set_var("CLAUDE_CONFIG_DIR", "");
+            assert_eq!(get_cli_config_path().unwrap(), home.join(".config/znotify/config.toml"));
+            assert_eq!(get_claude_settings_path().unwrap(), home.join(".claude/settings.json"));
+        });
+    }
+
+    #[test]
+    fn install_hooks_keeps_user_hooks_and_never_duplicates() {
+        in_home(|home| {
+            let claude = home.join(".claude");
+            fs::create_dir_all(&claude).unwrap();
+            let user_hook = json!({"matcher": "", "hooks": [{"type": "command", "command": "say done"}]});
+            fs::write(claude.join("settings.json"), json!({"hooks": {"Stop": [user_hook.clone()]}}).to_string()).unwrap();
+
+            claude_install_hooks(&["Stop".to_string()], false, false, false).unwrap();
+            claude_install_hooks(&["Stop".to_string()], false, false, false).unwrap();
+
+            let settings: Value = serde_json::from_str(&fs::read_to_string(claude.join("settings.json")).unwrap()).unwrap();
+            let groups = settings["hooks"]["Stop"].as_array().unwrap();
+            assert_eq!(groups[0], user_hook);
+            assert_eq!(hooks::hook_commands(groups).collect::<Vec<_>>(), ["say done", "znotify notify stop"]);
+        });
+    }
+
+    #[test]
+    fn notify_message_is_sent_as_a_pipe_arg() {
+        let args = notify_args(&["stop", "--message", "tests passed"]);
+        assert_eq!(notify_pipe_args(&args, None), vec![("message", "tests passed".to_string())]);
+        let args = notify_args(&["stop", "--message", ""]);
+        assert!(notify_pipe_args(&args, None).is_empty());
+    }
+
+    #[test]
+    fn query_replies_parse_into_pending_notifications() {
+        let pending = parse_pending("[{\"position\":2,\"name\":\"logs\",\"emoji\":\"✅×2\"}]\n").unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].position, pending[0].name.as_str(), pending[0].emoji.as_str()), (2, "logs", "✅×2"));
+        assert!(parse_pending("[]").unwrap().is_empty());
+        assert!(parse_pending("not json").is_err());
+    }
+
+    #[test]
+    fn version_prints_the_crate_version() {
+        let text = version_report(b"\0asm znotify-plugin-version:0.1.51;", false).unwrap();
+        assert_eq!(text, format!("znotify {}\nembedded plugin 0.1.51", env!("CARGO_PKG_VERSION")));
+
+        let report: Value = serde_json::from_str(&version_report(&[], true).unwrap()).unwrap();
+        assert_eq!(report, json!({"cli": env!("CARGO_PKG_VERSION"), "plugin": null}));
+    }
+
+    #[test]
+    fn missing_zellij_says_how_to_fix_it() {
+        let mut cmd = Command::new("/nonexistent/zellij");
+        cmd.arg("pipe");
+        let error = with_retry(3, Duration::ZERO, || run_pipe(&mut cmd)).unwrap_err();
+        assert_eq!(error.to_string(), ZELLIJ_NOT_FOUND);
+    }
+
+    #[test]
+    fn bell_rings_only_when_enabled() {
+        let mut bell = Vec::new();
+        write_bell(&mut bell).unwrap();
+        assert_eq!(bell, b"\x07");
+
+        let config = CliConfig { bell: vec!["stop".to_string()], ..Default::default() };
+        with_env(&[("ZNOTIFY_BELL", None)], || {
+            assert!(!rings_bell(&notify_args(&["notification"]), "notification", &CliConfig::default()));
+            assert!(rings_bell(&notify_args(&["notification", "--bell"]), "notification", &CliConfig::default()));
+            assert!(rings_bell(&notify_args(&["stop"]), "stop", &config));
+            assert!(!rings_bell(&notify_args(&["notification"]), "notification", &config));
+        });
+        with_env(&[("ZNOTIFY_BELL", Some(OsStr::new("1")))], || {
+            assert!(rings_bell(&notify_args(&["notification"]), "notification", &CliConfig::default()));
+        });
+    }
+
+    #[test]
+    fn explicit_session_targets_that_session() {
+        let (other, own) = in_zellij(|| (
+            build_pipe_command("notify", "stop", Some("work"), &[]).unwrap(),
+            build_pipe_command("notify", "stop", Some("main"), &[]).unwrap(),
+        ));
+        assert_eq!(words(&other), ["--session", "work", "pipe", "-n", "notify", "-a", "session_name=work", "stop"]);
+        // Our own session is just the usual pane-targeted pipe
+        assert_eq!(words(&own), [
+            "pipe", "-n", "notify", "-a", "pane_id=4", "-a", "session_name=main", "-a", "tab_name=build", "stop",
+        ]);
+    }
+
+    #[test]
+    fn status_reports_the_pending_count() {
+        let mut report = in_home(|home| collect_status(Some(&home.join("zellij")), None, "notify", &CliConfig::default()).unwrap());
+        // Outside Zellij there's no plugin to ask
+        assert_eq!(report.pending_notifications, None);
+        assert_eq!(serde_json::to_value(&report).unwrap()["pending_notifications"], Value::Null);
+
+        report.pending_notifications = Some(3);
+        assert_eq!(serde_json::to_value(&report).unwrap()["pending_notifications"], json!(3));
+    }
+
+    #[test]
+    fn mute_appends_to_the_plugin_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.kdl");
+        fs::write(&config, "plugins {\n    notify location=\"file:~/.config/zellij/plugins/zellij-notify.wasm\" {\n    }\n}\n").unwrap();
+
+        mute(Some(dir.path()), "2", false).unwrap();
+        mute(Some(dir.path()), " scratch* ", false).unwrap();
+        mute(Some(dir.path()), "2", false).unwrap();
+        let content = fs::read_to_string(&config).unwrap();
+        assert_eq!(kdl_config::read_option(&content, "mute_tabs").as_deref(), Some("2, scratch*"));
+
+        assert!(mute(Some(dir.path()), "a,b", false).is_err());
+    }
+
+    #[test]
+    fn install_config_adds_a_missing_block_to_load_plugins() {
+        in_home(|home| {
+            let dir = home.join("zellij");
+            fs::create_dir_all(&dir).unwrap();
+            let config = dir.join("config.kdl");
+            fs::write(&config, "theme \"dracula\"\n\nload_plugins {\n    compact-bar\n}\n").unwrap();
+
+            install_config(Some(&dir), &CliConfig::default(), false).unwrap();
+            let content = fs::read_to_string(&config).unwrap();
+            assert!(content.starts_with("theme \"dracula\"\n\nload_plugins {\n    compact-bar\n"));
+            assert!(kdl_config::find_plugin_block(&content).is_some());
+            assert_eq!(content.matches("load_plugins").count(), 1);
+            assert_eq!(files_starting_with(&dir, "config.kdl.").len(), 1);
+        });
+    }
+
+    #[test]
+    fn install_config_leaves_an_existing_block_alone() {
+        in_home(|home| {
+            let dir = home.join("zellij");
+            fs::create_dir_all(&dir).unwrap();
+            let config = dir.join("config.kdl");
+            let original = "load_plugins {\n    \"file:~/.config/zellij/plugins/zellij-notify.wasm\" {\n        debug \"true\"\n    }\n}\n";
+            fs::write(&config, original).unwrap();
+
+            install_config(Some(&dir), &CliConfig::default(), false).unwrap();
+            assert_eq!(fs::read_to_string(&config).unwrap(), original);
+            assert!(files_starting_with(&dir, "config.kdl.").is_empty());
+        });
+    }
+
+    #[test]
+    fn install_config_dry_run_writes_nothing() {
+        in_home(|home| {
+            let dir = home.join("zellij");
+            fs::create_dir_all(&dir).unwrap();
+            let config = dir.join("config.kdl");
+            fs::write(&config, "theme \"dracula\"\n").unwrap();
+
+            install_config(Some(&dir), &CliConfig::default(), true).unwrap();
+            assert_eq!(fs::read_to_string(&config).unwrap(), "theme \"dracula\"\n");
+            assert!(files_starting_with(&dir, "config.kdl.").is_empty());
+
+            // With no config.kdl at all, a fresh one holds just the block
+            fs::remove_file(&config).unwrap();
+            install_config(Some(&dir), &CliConfig::default(), false).unwrap();
+            let content = fs::read_to_string(&config).unwrap();
+            assert!(content.starts_with("load_plugins {\n"));
+            assert!(kdl_config::find_plugin_block(&content).is_some());
+        });
+    }
+
+    #[test]
+    fn version_reports_only_a_bundled_plugin() {
+        assert_eq!(bundled_plugin_version(&[]), None);
+        assert_eq!(bundled_plugin_version(b"\0asm znotify-plugin-version:9.8.7; rest").as_deref(), Some("9.8.7"));
+        assert_eq!(bundled_plugin_version(b"\0asm no marker").as_deref(), Some(PLUGIN_VERSION));
+    }
+
+    #[test]
+    fn config_template_presets_match_the_notify_table() {
+        let cli_config = CliConfig {
+            presets: [("stop".to_string(), "🏁".to_string()), ("review".to_string(), "👀".to_string())].into(),
+            ..Default::default()
+        };
+        let template = in_home(|home| config_template(Some(&home.join("zellij")), &cli_config).unwrap());
+        let presets = kdl_config::read_presets(&template).unwrap().unwrap();
+
+        let generated: Vec<(String, String)> = presets.iter()
+            .map(|(name, preset)| (name.clone(), preset["emoji"].as_str().unwrap().to_string()))
+            .collect();
+        let table = notifications(&cli_config);
+        assert_eq!(generated, table);
+        assert!(table.contains(&("stop".to_string(), "🏁".to_string())));
+        assert!(table.contains(&("review".to_string(), "👀".to_string())));
+    }
+
+    #[test]
+    fn logs_prints_the_last_lines_of_the_log_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("zellij-notify.log");
+        let fixture = "[INFO] 🔔 PIPE: notify\n[DEBUG] 🧭 Resolving target\n[INFO] ✅ Renamed tab 1\n";
+        fs::write(&log, fixture).unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(write_log(&log, Some(2), &mut out).unwrap(), fixture.len() as u64);
+        assert_eq!(String::from_utf8(out).unwrap(), "[DEBUG] 🧭 Resolving target\n[INFO] ✅ Renamed tab 1\n");
+
+        let mut out = Vec::new();
+        write_log(&log, None, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), fixture);
+
+        // The plugin block's log_file is where logs looks without --file
+        fs::write(dir.path().join("config.kdl"), format!(
+            "plugins {{\n    notify location=\"file:~/.config/zellij/plugins/zellij-notify.wasm\" {{\n        log_file \"{}\"\n    }}\n}}\n",
+            log.display())).unwrap();
+        assert_eq!(plugin_log_file(Some(dir.path())).unwrap(), log);
+
+        let missing = write_log(&dir.path().join("missing.log"), None, &mut Vec::new()).unwrap_err();
+        assert!(missing.to_string().contains("log_file is set"));
+    }
+
+    #[test]
+    fn home_falls_back_to_userprofile_without_home() {
+        let profile = tempfile::tempdir().unwrap();
+        let vars = [
+            ("HOME", None),
+            ("USERPROFILE", Some(profile.path().as_os_str())),
+            ("HOMEDRIVE", None),
+            ("HOMEPATH", None),
+            ("XDG_CONFIG_HOME", None),
+            ("CLAUDE_CONFIG_DIR", None),
+            ("ZELLIJ_CONFIG_DIR", None),
+        ];
+        with_env(&vars, || {
+            assert_eq!(home_dir().unwrap(), profile.path());
+            assert_eq!(get_claude_settings_path().unwrap(), profile.path().join(".claude").join("settings.json"));
+            assert_eq!(get_plugin_path(None).unwrap(),
+                       profile.path().join(".config").join("zellij").join("plugins").join("zellij-notify.wasm"));
+        });
+
+        let vars = [("HOME", None), ("USERPROFILE", None), ("HOMEDRIVE", None), ("HOMEPATH", None)];
+        let error = with_env(&vars, || home_dir().unwrap_err());
+        assert!(error.to_string().contains("set HOME (or USERPROFILE on Windows)"));
+    }
+
+    #[test]
+    fn uninstall_hooks_removes_only_znotify_commands() {
+        let mut settings = json!({
+            "hooks": {
+                "Notification": [{"hooks": [{"type": "command", "command": "say done"}]}],
+                "Stop": [{"hooks": [
+                    {"type": "command", "command": "znotify notify stop"},
+                    {"type": "command", "command": "./scripts/lint.sh"}
+                ]}],
+                "PostToolUse": [{"matcher": "Edit", "hooks": [{"type": "command", "command": "znotify notify posttooluse"}]}]
+            }
+        });
+        let hooks = settings["hooks"].as_object_mut().unwrap();
+
+        let (removed, missing) = remove_znotify_hooks(hooks);
+        assert_eq!(removed, vec!["Stop", "PostToolUse"]);
+        assert_eq!(missing, CLAUDE_HOOKS.iter()
+            .map(|(event, _, _)| *event)
+            .filter(|event| !removed.contains(event))
+            .collect::<Vec<_>>());
+        assert_eq!(settings, json!({
+            "hooks": {
+                "Notification": [{"hooks": [{"type": "command", "command": "say done"}]}],
+                "Stop": [{"hooks": [{"type": "command", "command": "./scripts/lint.sh"}]}]
+            }
+        }));
+    }
+
+    #[test]
+    fn test_emoji_skips_ones_a_preset_uses() {
+        let table = |emojis: &[&str]| -> Vec<(String, String)> {
+            emojis.iter().enumerate().map(|(i, emoji)| (format!("preset{}", i), emoji.to_string())).collect()
+        };
+        assert_eq!(test_emoji(&notifications(&CliConfig::default())), Some("🧪"));
+        assert_eq!(test_emoji(&table(&["✅", "🧪"])), Some("🔬"));
+        assert_eq!(test_emoji(&table(&["🧪", "🔬"])), Some("🛸"));
+        assert_eq!(test_emoji(&table(&["🛸", "🔬", "🧪"])), None);
+    }
+
+    #[test]
+    fn install_path_is_written_and_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = resolve_plugin_path(None, Some(&dir.path().join("plugins").join("notify.wasm"))).unwrap();
+        write_plugin(&path, b"\0asm").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"\0asm");
+
+        let reload = reload_command(&path);
+        assert_eq!(reload.get_program(), "zellij");
+        assert_eq!(words(&reload), ["action", "start-or-reload-plugin", &format!("file:{}", path.display())]);
+
+        // A directory gets the usual file name inside it
+        assert_eq!(resolve_plugin_path(None, Some(dir.path())).unwrap(), dir.path().join("zellij-notify.wasm"));
+    }
+
+    #[test]
+    fn clear_emoji_goes_out_as_a_pipe_arg() {
+        let cli = Cli::try_parse_from(["znotify", "clear", "--emoji", "❌"]).unwrap();
+        let Commands::Clear { emoji } = cli.command else {
+            panic!("not a clear command");
+        };
+        let cmd = in_zellij(|| build_pipe_command("notify-clear", "", None, &clear_args(emoji.as_deref())).unwrap());
+        assert_eq!(words(&cmd), [
+            "pipe", "-n", "notify-clear", "-a", "pane_id=4", "-a", "session_name=main", "-a", "tab_name=build",
+            "-a", "emoji=❌", "",
+        ]);
+
+        assert!(clear_args(None).is_empty());
+        assert!(clear_args(Some("")).is_empty());
+    }
+
+    #[test]
+    fn retries_only_transient_pipe_failures() {
+        // Fails the way the script says, then succeeds once it runs out
+        let runner = |script: &'static [bool]| {
+            let mut runs = 0;
+            move || {
+                runs += 1;
+                match script.get(runs - 1) {
+                    Some(true) => Err(PipeFailure::Transient(anyhow!("busy"))),
+                    Some(false) => Err(PipeFailure::Fatal(anyhow!("no such session"))),
+                    None => Ok(runs),
+                }
+            }
+        };
+
+        assert_eq!(with_retry(3, Duration::ZERO, runner(&[true, true])).unwrap(), 3);
+        assert_eq!(with_retry(3, Duration::ZERO, runner(&[true, true, true])).unwrap_err().to_string(), "busy");
+        assert_eq!(with_retry(3, Duration::ZERO, runner(&[true, false])).unwrap_err().to_string(), "no such session");
+        assert_eq!(with_retry(1, Duration::ZERO, runner(&[true])).unwrap_err().to_string(), "busy");
+
+        // Used-up attempts stop the loop even while it keeps failing
+        let mut runs = 0;
+        let always_busy = || -> Result<(), PipeFailure> {
+            runs += 1;
+            Err(PipeFailure::Transient(anyhow!("busy")))
+        };
+        assert!(with_retry(4, Duration::ZERO, always_busy).is_err());
+        assert_eq!(runs, 4);
+    }
+
+    #[test]
+    fn init_runs_every_step_and_can_run_again() {
+        in_home(|home| {
+            let dir = home.join(".config").join("zellij");
+            let settings = home.join(".claude").join("settings.json");
+            let run = || {
+                let result = init(Some(&dir), &CliConfig::default(), false);
+                // The plugin step can only work when the wasm was bundled into this build
+                if WASM_BYTES.is_empty() {
+                    assert_eq!(result.unwrap_err().to_string(),
+                               "1 of 3 setup steps failed; fix the errors above and run znotify init again");
+                } else {
+                    result.unwrap();
+                    assert_eq!(fs::read(dir.join("plugins").join("zellij-notify.wasm")).unwrap(), WASM_BYTES);
+                }
+                (fs::read_to_string(dir.join("config.kdl")).unwrap(), fs::read_to_string(&settings).unwrap())
+            };
+
+            let (config, claude) = run();
+            assert!(kdl_config::find_plugin_block(&config).is_some());
+            let claude: Value = serde_json::from_str(&claude).unwrap();
+            assert_eq!(claude["hooks"]["Stop"][0]["hooks"][0]["command"], "znotify notify stop");
+
+            // Everything was already done, so nothing changes
+            let (again, claude_again) = run();
+            assert_eq!(again, config);
+            assert_eq!(serde_json::from_str::<Value>(&claude_again).unwrap(), claude);
+        });
+    }
+
+    #[test]
+    fn tabs_flag_sends_tab_positions() {
+        let args = notify_pipe_args(&notify_args(&["stop", "--tabs", "1,3", "--tabs", "5"]), None);
+        assert!(args.contains(&("tab_positions", "1,3,5".to_string())));
+        assert!(Cli::try_parse_from(["znotify", "notify", "stop", "--tabs", "1,x"]).is_err());
+    }
+
+    #[test]
+    fn plugin_change_compares_version_markers() {
+        let old: &[u8] = b"\0asm...znotify-plugin-version:0.1.9;...";
+        let new: &[u8] = b"\0asm...znotify-plugin-version:0.1.10;...";
+        let rebuilt: &[u8] = b"\0asm.rebuilt.znotify-plugin-version:0.1.10;...";
+        let unmarked: &[u8] = b"\0asm...";
+
+        assert_eq!(plugin_change(None, new), PluginChange::Fresh);
+        assert_eq!(plugin_change(Some(new), new), PluginChange::Unchanged);
+        assert_eq!(plugin_change(Some(old), new),
+                   PluginChange::Upgrade { from: "0.1.9".to_string(), to: "0.1.10".to_string() });
+        assert_eq!(plugin_change(Some(new), old),
+                   PluginChange::Downgrade { from: "0.1.10".to_string(), to: "0.1.9".to_string() });
+        assert_eq!(plugin_change(Some(rebuilt), new), PluginChange::Rebuild { version: Some("0.1.10".to_string()) });
+        // Without a marker on both sides only the bytes can be compared
+        assert_eq!(plugin_change(Some(unmarked), new), PluginChange::Rebuild { version: None });
+        assert_eq!(plugin_change(Some(unmarked), unmarked), PluginChange::Unchanged);
+    }
+
+    #[test]
+    fn exit_codes_pick_the_emoji() {
+        assert_eq!(exit_emoji(0), "✅");
+        for code in [1, 2, 127, -1] {
+            assert_eq!(exit_emoji(code), "❌");
+        }
+
+        assert_eq!(override_emoji(&notify_args(&["posttooluse", "--exit", "0"])), Some("✅"));
+        assert_eq!(override_emoji(&notify_args(&["posttooluse", "--exit=-1"])), Some("❌"));
+        assert_eq!(override_emoji(&notify_args(&["posttooluse", "--emoji", "🚧"])), Some("🚧"));
+        assert!(Cli::try_parse_from(["znotify", "notify", "posttooluse", "--exit", "1", "--emoji", "🚧"]).is_err());
+        assert_eq!(override_emoji(&notify_args(&["posttooluse"])), None);
+
+        let args = notify_args(&["posttooluse", "--exit", "3"]);
+        assert!(notify_pipe_args(&args, override_emoji(&args)).contains(&("emoji", "❌".to_string())));
+    }
+
+    #[test]
+    fn broken_claude_settings_need_force() {
+        in_home(|home| {
+            let settings = home.join(".claude").join("settings.json");
+            fs::create_dir_all(settings.parent().unwrap()).unwrap();
+            let broken = "{\"hooks\": {\"Stop\": [,]}";
+            fs::write(&settings, broken).unwrap();
+
+            let error = claude_install_hooks(&[], true, false, false).unwrap_err().to_string();
+            assert!(error.starts_with(&format!("{} is not valid JSON", settings.display())), "{}", error);
+            assert!(error.contains("--force"));
+            assert_eq!(fs::read_to_string(&settings).unwrap(), broken);
+
+            let report = collect_status(Some(&home.join("zellij")), None, "notify", &CliConfig::default()).unwrap();
+            assert!(!report.hooks_installed);
+            assert!(report.claude_settings_error.unwrap().starts_with("not valid JSON: "));
+
+            // --force keeps the broken file as a backup and starts over
+            claude_install_hooks(&[], false, true, false).unwrap();
+            let backups = files_starting_with(settings.parent().unwrap(), "settings.json.");
+            assert_eq!(backups.len(), 1);
+            assert_eq!(fs::read_to_string(settings.parent().unwrap().join(&backups[0])).unwrap(), broken);
+            let fresh: Value = serde_json::from_str(&fs::read_to_string(&settings).unwrap()).unwrap();
+            assert_eq!(fresh["hooks"]["Stop"][0]["hooks"][0]["command"], "znotify notify stop");
+        });
+    }
+
+    #[test]
+    fn goto_sends_list_positions_as_tab_numbers() {
+        assert_eq!(goto_target("0"), ("tab_number", "1".to_string()));
+        assert_eq!(goto_target("4"), ("tab_number", "5".to_string()));
+        assert_eq!(goto_target("build"), ("tab_name", "build".to_string()));
+    }
+
+    #[test]
+    fn notify_list_has_every_builtin_name() {
+        let lines = notification_list(&CliConfig::default(), false).unwrap();
+        for (name, emoji) in NOTIFY_CONFIG {
+            assert!(lines.lines().any(|line| line == format!("{}\t{}", name, emoji)), "{} missing", name);
+        }
+        assert_eq!(lines.lines().count(), NOTIFY_CONFIG.len());
+
+        let cli_config = CliConfig { presets: [("deploy".to_string(), "🚀".to_string())].into(), ..Default::default() };
+        let list: Value = serde_json::from_str(&notification_list(&cli_config, true).unwrap()).unwrap();
+        let list = list.as_array().unwrap();
+        assert_eq!(list.len(), NOTIFY_CONFIG.len() + 1);
+        assert_eq!(list[0], json!({"name": NOTIFY_CONFIG[0].0, "emoji": NOTIFY_CONFIG[0].1}));
+        assert!(list.contains(&json!({"name": "deploy", "emoji": "🚀"})));
+    }
+
+    #[test]
+    fn set_name_goes_out_as_a_pipe_arg() {
+        let args = notify_pipe_args(&notify_args(&["stop", "--set-name", "deploy api"]), None);
+        assert!(args.contains(&("set_name", "deploy api".to_string())));
+        let args = notify_pipe_args(&notify_args(&["stop", "--set-name", ""]), None);
+        assert!(!args.iter().any(|(key, _)| *key == "set_name"));
+    }
+
+    #[test]
+    fn tab_flag_sends_the_bar_number() {
+        let args = notify_pipe_args(&notify_args(&["stop", "--tab", "3"]), None);
+        assert!(args.contains(&("tab_number", "3".to_string())));
+        assert!(!args.iter().any(|(key, _)| *key == "tab_position"));
+        assert!(Cli::try_parse_from(["znotify", "notify", "stop", "--tab", "3", "--all"]).is_err());
+    }
+
+    #[test]
+    fn auto_install_only_when_asked_in_zellij_and_missing() {
+        in_home(|home| {
+            let dir = home.join("zellij");
+            let plugin = dir.join("plugins").join("zellij-notify.wasm");
+            let asked = notify_args(&["stop", "--auto-install"]);
+            let from_config = CliConfig { auto_install: true, ..Default::default() };
+
+            // Outside Zellij there's no one to load it
+            assert_eq!(missing_plugin(&asked, Some(&dir), &CliConfig::default()), None);
+
+            env::set_var("ZELLIJ", "0");
+            assert_eq!(missing_plugin(&asked, Some(&dir), &CliConfig::default()), Some(plugin.clone()));
+            assert_eq!(missing_plugin(&notify_args(&["stop"]), Some(&dir), &from_config), Some(plugin.clone()));
+            assert_eq!(missing_plugin(&notify_args(&["stop"]), Some(&dir), &CliConfig::default()), None);
+
+            write_plugin(&plugin, b"\0asm").unwrap();
+            assert_eq!(missing_plugin(&asked, Some(&dir), &CliConfig::default()), None);
+            env::remove_var("ZELLIJ");
+        });
+    }
+
+    #[test]
+    fn echo_line_describes_where_the_notification_went() {
+        let line = |flags: &[&str], pane, session, tab| {
+            let args = notify_args(&[&["stop"], flags].concat());
+            echo_line("stop", "✅", &args, pane, session, tab)
+        };
+        assert_eq!(line(&[], Some("4"), Some("main"), Some("build")), "notified stop (✅) -> pane 4 session main tab build");
+        assert_eq!(line(&[], None, Some("main"), Some("")), "notified stop (✅) -> pane ? session main tab ?");
+        assert_eq!(line(&["--all"], Some("4"), Some("main"), Some("build")), "notified stop (✅) -> all tabs session main");
+        assert_eq!(line(&["--tabs", "1,3"], Some("4"), Some("main"), None), "notified stop (✅) -> tabs 1,3 session main");
+        assert_eq!(line(&["--tab", "2"], Some("4"), None, None), "notified stop (✅) -> tab #2 session ?");
+        assert_eq!(line(&["--session", "work"], Some("4"), Some("work"), None), "notified stop (✅) -> active tab session work");
+    }
+
+    #[test]
+    fn agent_goes_out_as_a_pipe_arg() {
+        let args = notify_pipe_args(&notify_args(&["subagent-stop", "--agent", "reviewer"]), None);
+        assert!(args.contains(&("agent", "reviewer".to_string())));
+        let args = notify_pipe_args(&notify_args(&["subagent-stop"]), None);
+        assert!(!args.iter().any(|(key, _)| *key == "agent"));
+    }
+
+    #[test]
+    fn validate_presets_accepts_what_the_plugin_loads() {
+        assert!(validate_presets(r#"{"stop": {"emoji": "✅"}, "deploy": {"emoji": "🚀", "color": "pink"}}"#).is_ok());
+        assert!(validate_presets("{}").is_ok());
+    }
+
+    #[test]
+    fn validate_presets_rejects_what_the_plugin_skips() {
+        assert!(validate_presets(r#"{"stop": {"emoji": ""}}"#).is_err());
+        assert!(validate_presets(r#"{"stop": {"emoji": "✅✅✅✅✅✅"}}"#).is_err());
+        assert!(validate_presets(r#"{"stop": {"emoji": "✅"}, "bad": {"emoji": 3}}"#).is_err());
+        assert!(validate_presets(r#"{"stop": "#).is_err());
+        assert!(validate_presets(r#"["✅"]"#).is_err());
+    }
 }
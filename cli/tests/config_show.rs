@@ -0,0 +1,40 @@
+// `config show --json` picking up a config.toml in a temp home
+
+use serde_json::{json, Value};
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn toml_overrides_show_with_their_source() {
+    let home = tempfile::tempdir().unwrap();
+    let config = home.path().join(".config").join("znotify").join("config.toml");
+    fs::create_dir_all(config.parent().unwrap()).unwrap();
+    fs::write(&config, "history = true\n\n[presets]\nstop = \"🏁\"\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_znotify"))
+        .args(["config", "show", "--json"])
+        .env("HOME", home.path())
+        .env_remove("ZELLIJ")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_STATE_HOME")
+        .env_remove("ZELLIJ_CONFIG_DIR")
+        .env_remove("CLAUDE_CONFIG_DIR")
+        .env_remove("ZNOTIFY_PIPE_NAME")
+        .env_remove("ZNOTIFY_DESKTOP_FALLBACK")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(report["settings"]["history"], json!({"value": true, "source": "config.toml"}));
+    assert_eq!(report["settings"]["auto_install"], json!({"value": false, "source": "default"}));
+    assert_eq!(report["paths"]["cli_config"], json!({"value": config.display().to_string(), "source": "default"}));
+
+    let notification = |name: &str| report["notifications"].as_array().unwrap().iter()
+        .find(|n| n["name"] == name)
+        .cloned()
+        .unwrap();
+    assert_eq!(notification("stop")["emoji"], "🏁");
+    assert_eq!(notification("stop")["source"], "config.toml, overrides ✅");
+    assert_eq!(notification("notification")["source"], "built-in");
+}
@@ -0,0 +1,45 @@
+// Runs the built binary, since `--quiet` is about what reaches stdout
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn znotify(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_znotify"))
+        .args(args)
+        .env("HOME", home)
+        .env_remove("ZELLIJ")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_STATE_HOME")
+        .env_remove("ZELLIJ_CONFIG_DIR")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn quiet_install_prints_nothing_on_success() {
+    let home = tempfile::tempdir().unwrap();
+    let config_dir = home.path().join("zellij");
+    let config_dir = config_dir.to_str().unwrap();
+
+    let output = znotify(home.path(), &["--quiet", "--config-dir", config_dir, "install-config"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert!(fs::read_to_string(home.path().join("zellij").join("config.kdl")).unwrap().contains("zellij-notify.wasm"));
+
+    // Without it, the same (now idempotent) install reports what it found
+    let output = znotify(home.path(), &["--config-dir", config_dir, "install-config"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("already has a znotify plugin block"));
+}
+
+#[test]
+fn quiet_still_reports_errors() {
+    let home = tempfile::tempdir().unwrap();
+    fs::write(home.path().join("zellij"), "not a directory").unwrap();
+    let config_dir = home.path().join("zellij");
+
+    let output = znotify(home.path(), &["--quiet", "--config-dir", config_dir.to_str().unwrap(), "install-config"]);
+    assert!(!output.status.success());
+    assert!(!output.stderr.is_empty());
+}
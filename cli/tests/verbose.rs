@@ -0,0 +1,50 @@
+// `notify --verbose` against a stand-in `zellij` that accepts any pipe
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::process::{Command, Output};
+
+fn notify(args: &[&str]) -> Output {
+    let home = tempfile::tempdir().unwrap();
+    let bin = home.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    let zellij = bin.join("zellij");
+    fs::write(&zellij, "#!/bin/sh\nexit 0\n").unwrap();
+    fs::set_permissions(&zellij, fs::Permissions::from_mode(0o755)).unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_znotify"))
+        .arg("notify")
+        .args(args)
+        .env("HOME", home.path())
+        .env("PATH", &bin)
+        .env("ZELLIJ", "0")
+        .env("ZELLIJ_PANE_ID", "4")
+        .env("ZELLIJ_SESSION_NAME", "main")
+        .env("ZELLIJ_TAB_NAME", "my build")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_STATE_HOME")
+        .env_remove("ZELLIJ_CONFIG_DIR")
+        .env_remove("ZNOTIFY_PIPE_NAME")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn verbose_prints_the_pipe_command() {
+    let output = notify(&["stop", "--verbose"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(
+        "[znotify] Running: zellij pipe -n notify -a pane_id=4 -a session_name=main -a 'tab_name=my build' stop\n"
+    ), "{}", stderr);
+    assert!(stderr.contains("[znotify] zellij exit status: 0\n"), "{}", stderr);
+}
+
+#[test]
+fn without_verbose_success_is_silent() {
+    let output = notify(&["stop"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
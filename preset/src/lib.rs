@@ -0,0 +1,183 @@
+// Preset definitions as the plugin reads them from `presets` and `presets_file`, and the
+// checks it runs before using one. A crate of its own, free of zellij-tile, so the CLI's
+// `validate-presets` parses and judges presets exactly the way the plugin will.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Longest preset emoji accepted, in grapheme clusters; room for an ASCII marker like "[err]".
+pub const MAX_EMOJI_GRAPHEMES: usize = 5;
+
+// Zellij's rename_tab only takes plain text (escape codes would show up literally in the
+// tab bar), so a preset's color is rendered as the matching colored circle
+const COLOR_MARKERS: &[(&str, &str)] = &[
+    ("red", "🔴"),
+    ("orange", "🟠"),
+    ("yellow", "🟡"),
+    ("green", "🟢"),
+    ("blue", "🔵"),
+    ("purple", "🟣"),
+    ("brown", "🟤"),
+    ("black", "⚫"),
+    ("white", "⚪"),
+];
+
+/// One preset: the emoji a notification name shows, plus its optional extras.
+#[derive(Deserialize, Clone, Default)]
+pub struct PresetConfig {
+    pub emoji: String,
+    #[serde(default)]
+    pub label: Option<String>,  // Optional text shown after the emoji, e.g. "done"
+    #[serde(default)]
+    pub priority: Option<u8>,  // Higher wins over a lower one already on the tab (default 0)
+    #[serde(default)]
+    pub foreground_emoji: Option<String>,  // Used instead of `emoji` when the target tab is focused
+    #[serde(default)]
+    pub background_emoji: Option<String>,  // Used instead of `emoji` when the target tab is in the background
+    #[serde(default)]
+    pub color: Option<String>,  // Severity color, shown as a colored circle in front of the emoji
+    #[serde(default)]
+    pub clear_after_secs: Option<u64>,  // Overrides the global auto-clear timeout for this preset (0 = never)
+}
+
+/// Something wrong with a preset that parsed, and what the plugin does about it.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Problem {
+    /// The emoji would garble the tab name; the whole preset is skipped
+    Emoji(String),
+    /// A focus variant is unusable; the main emoji is shown instead
+    Variant(&'static str, String),
+    /// The color has no circle; the emoji is shown without one
+    Color(String),
+}
+
+impl Problem {
+    /// Whether the plugin drops the preset over it.
+    pub fn skips_preset(&self) -> bool {
+        matches!(self, Problem::Emoji(_))
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Emoji(reason) => write!(f, "emoji {}", reason),
+            Problem::Variant(field, reason) => write!(f, "{} {}", field, reason),
+            Problem::Color(color) => write!(f, "unknown color '{}'", color),
+        }
+    }
+}
+
+/// Preset name -> preset, from a JSON object.
+pub fn parse(json: &str) -> serde_json::Result<HashMap<String, PresetConfig>> {
+    serde_json::from_str(json)
+}
+
+/// Everything the plugin would skip or ignore in `preset`, in the order it checks.
+/// A bad main emoji is the only problem reported, since the rest is never looked at.
+pub fn problems(preset: &PresetConfig) -> Vec<Problem> {
+    if let Some(reason) = invalid_emoji_reason(&preset.emoji) {
+        return vec![Problem::Emoji(reason)];
+    }
+
+    let mut problems = Vec::new();
+    for (field, variant) in [
+        ("foreground_emoji", &preset.foreground_emoji),
+        ("background_emoji", &preset.background_emoji),
+    ] {
+        if let Some(reason) = variant.as_deref().and_then(invalid_emoji_reason) {
+            problems.push(Problem::Variant(field, reason));
+        }
+    }
+    if let Some(color) = preset.color.as_deref().filter(|c| color_marker(c).is_none()) {
+        problems.push(Problem::Color(color.to_string()));
+    }
+    problems
+}
+
+/// Why `emoji` can't go on a tab, if it can't: it's blank or longer than MAX_EMOJI_GRAPHEMES.
+pub fn invalid_emoji_reason(emoji: &str) -> Option<String> {
+    if emoji.trim().is_empty() {
+        return Some("is empty".to_string());
+    }
+    let count = emoji.graphemes(true).count();
+    if count > MAX_EMOJI_GRAPHEMES {
+        return Some(format!("is {} characters long (max {})", count, MAX_EMOJI_GRAPHEMES));
+    }
+    None
+}
+
+/// The colored circle for a color name, case-insensitive.
+pub fn color_marker(color: &str) -> Option<&'static str> {
+    let color = color.trim().to_lowercase();
+    COLOR_MARKERS.iter()
+        .find(|(name, _)| *name == color)
+        .map(|(_, marker)| *marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_valid_presets() {
+        let presets = parse(r#"{"stop": {"emoji": "✅", "label": "done", "priority": 2, "color": "Red"}}"#).unwrap();
+        let stop = &presets["stop"];
+        assert_eq!(stop.emoji, "✅");
+        assert_eq!(stop.label.as_deref(), Some("done"));
+        assert_eq!(stop.priority, Some(2));
+        assert!(problems(stop).is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_presets() {
+        assert!(parse(r#"{"stop": {"label": "no emoji"}}"#).is_err());
+        assert!(parse(r#"{"stop": {"emoji": 3}}"#).is_err());
+        assert!(parse(r#"["✅"]"#).is_err());
+        assert!(parse(r#"{"stop": "#).is_err());
+    }
+
+    #[test]
+    fn bad_emoji_skips_the_preset() {
+        let empty = PresetConfig { emoji: "  ".into(), color: Some("pink".into()), ..Default::default() };
+        assert_eq!(problems(&empty), vec![Problem::Emoji("is empty".into())]);
+        assert!(problems(&empty)[0].skips_preset());
+
+        let long = PresetConfig { emoji: "abcdef".into(), ..Default::default() };
+        assert_eq!(problems(&long), vec![Problem::Emoji("is 6 characters long (max 5)".into())]);
+    }
+
+    #[test]
+    fn bad_extras_are_only_ignored() {
+        let preset = PresetConfig {
+            emoji: "✅".into(),
+            foreground_emoji: Some(String::new()),
+            background_emoji: Some("🟡".into()),
+            color: Some("pink".into()),
+            ..Default::default()
+        };
+        let found = problems(&preset);
+        assert_eq!(found, vec![
+            Problem::Variant("foreground_emoji", "is empty".into()),
+            Problem::Color("pink".into()),
+        ]);
+        assert!(!found.iter().any(Problem::skips_preset));
+        assert_eq!(found[0].to_string(), "foreground_emoji is empty");
+    }
+
+    #[test]
+    fn counts_graphemes_not_chars() {
+        // A ZWJ family is a single grapheme
+        assert_eq!(invalid_emoji_reason("👨‍👩‍👧"), None);
+        assert_eq!(invalid_emoji_reason("[err]"), None);
+        assert!(invalid_emoji_reason("[error]").is_some());
+    }
+
+    #[test]
+    fn color_names_are_case_insensitive() {
+        assert_eq!(color_marker(" RED "), Some("🔴"));
+        assert_eq!(color_marker("pink"), None);
+    }
+}
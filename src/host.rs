@@ -0,0 +1,31 @@
+// The tab renames and switches the plugin asks of Zellij, by 0-based `TabInfo.position`.
+// Under test they're recorded for tests::calls instead of reaching the host.
+
+pub(crate) use imp::{rename_tab_at, switch_to_tab_at};
+
+#[cfg(not(test))]
+mod imp {
+    use zellij_tile::prelude::{rename_tab, switch_tab_to};
+
+    // Both calls count tabs from 1
+    pub(crate) fn rename_tab_at(position: usize, name: String) {
+        rename_tab(position as u32 + 1, name);
+    }
+
+    pub(crate) fn switch_to_tab_at(position: usize) {
+        switch_tab_to(position as u32 + 1);
+    }
+}
+
+#[cfg(test)]
+mod imp {
+    use crate::tests::{record, Call};
+
+    pub(crate) fn rename_tab_at(position: usize, name: String) {
+        record(Call::Rename(position, name));
+    }
+
+    pub(crate) fn switch_to_tab_at(position: usize) {
+        record(Call::Switch(position));
+    }
+}
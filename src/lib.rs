@@ -1,16 +1,280 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
 use zellij_tile::prelude::*;
-use serde::Deserialize;
+use serde::Serialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+mod host;
+pub mod naming;
+mod target;
+#[cfg(test)]
+mod tests;
+
+use naming::{apply, is_rendered, strip_emoji, strip_emojis, EmojiPosition};
+use zellij_notify_preset::{self as preset, color_marker, invalid_emoji_reason, PresetConfig, Problem};
+use target::{resolve_target, TargetView};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-// Manual WASM entry point for cdylib
+// Emoji used for an empty payload and for an unknown preset key, unless configured
+const DEFAULT_EMOJI: &str = "✅";
+const UNKNOWN_EMOJI: &str = "❓";
+
+// Emoji for a command pane that exited with status 0 / anything else, unless configured
+const COMMAND_SUCCESS_EMOJI: &str = "✅";
+const COMMAND_FAILURE_EMOJI: &str = "❌";
+
+// Most tabs a single broadcast notification will rename
+const MAX_BROADCAST_TABS: usize = 50;
+
+// Notifications by pane_id that arrive before the first tabs and PaneManifest are held back,
+// at most this many and for at most this long, then replayed
+const MAX_QUEUED_MESSAGES: usize = 16;
+const QUEUED_MESSAGE_MAX_AGE_SECS: f64 = 30.0;
+
+// Presets used when the config has no `presets` key; same names and emojis as the CLI's table
+const DEFAULT_PRESETS: &[(&str, &str)] = &[
+    ("notification", "⚡"),
+    ("posttooluse", "⚡"),
+    ("stop", "✅"),
+    ("subagent-stop", "🔴"),
+    ("pretooluse", "🔧"),
+    ("userpromptsubmit", "💬"),
+    ("sessionstart", "🚀"),
+    ("sessionend", "🏁"),
+];
+
+// The `ascii` theme: monochrome symbols for terminals with poor emoji rendering
+const ASCII_PRESETS: &[(&str, &str)] = &[
+    ("notification", "!"),
+    ("posttooluse", "!"),
+    ("stop", "✓"),
+    ("subagent-stop", "✗"),
+    ("pretooluse", "*"),
+    ("userpromptsubmit", ">"),
+    ("sessionstart", "+"),
+    ("sessionend", "-"),
+];
+
+// The `text` theme, also picked by `no_emoji`: plain ASCII for terminals that show emoji as boxes
+const TEXT_PRESETS: &[(&str, &str)] = &[
+    ("notification", "[!]"),
+    ("posttooluse", "[!]"),
+    ("stop", "[ok]"),
+    ("subagent-stop", "[err]"),
+    ("pretooluse", "[*]"),
+    ("userpromptsubmit", "[>]"),
+    ("sessionstart", "[+]"),
+    ("sessionend", "[-]"),
+];
+
+// The `nerdfont` theme: Font Awesome glyphs from a patched Nerd Font
+const NERDFONT_PRESETS: &[(&str, &str)] = &[
+    ("notification", "\u{f0e7}"),
+    ("posttooluse", "\u{f0e7}"),
+    ("stop", "\u{f00c}"),
+    ("subagent-stop", "\u{f111}"),
+    ("pretooluse", "\u{f0ad}"),
+    ("userpromptsubmit", "\u{f075}"),
+    ("sessionstart", "\u{f135}"),
+    ("sessionend", "\u{f11e}"),
+];
+
+// A built-in set of indicators, picked with the `theme` config
+struct Theme {
+    name: &'static str,
+    default_emoji: &'static str,
+    unknown_emoji: &'static str,
+    command_success_emoji: &'static str,
+    command_failure_emoji: &'static str,
+    presets: &'static [(&'static str, &'static str)],
+}
+
+// The first theme is the default
+const THEMES: &[Theme] = &[
+    Theme {
+        name: "emoji",
+        default_emoji: DEFAULT_EMOJI,
+        unknown_emoji: UNKNOWN_EMOJI,
+        command_success_emoji: COMMAND_SUCCESS_EMOJI,
+        command_failure_emoji: COMMAND_FAILURE_EMOJI,
+        presets: DEFAULT_PRESETS,
+    },
+    Theme {
+        name: "ascii",
+        default_emoji: "✓",
+        unknown_emoji: "?",
+        command_success_emoji: "✓",
+        command_failure_emoji: "✗",
+        presets: ASCII_PRESETS,
+    },
+    Theme {
+        name: "text",
+        default_emoji: "[ok]",
+        unknown_emoji: "[?]",
+        command_success_emoji: "[ok]",
+        command_failure_emoji: "[err]",
+        presets: TEXT_PRESETS,
+    },
+    Theme {
+        name: "nerdfont",
+        default_emoji: "\u{f00c}",
+        unknown_emoji: "\u{f128}",
+        command_success_emoji: "\u{f00c}",
+        command_failure_emoji: "\u{f00d}",
+        presets: NERDFONT_PRESETS,
+    },
+];
+
+// Widest `message` arg shown on a tab, in columns
+const MAX_MESSAGE_LEN: usize = 40;
+
+// Keys that clear every notification / open the tab picker while the plugin pane is focused,
+// unless configured
+const DEFAULT_CLEAR_KEY: char = 'c';
+const DEFAULT_PICKER_KEY: char = 'p';
+
+/// Manual WASM entry point for cdylib
+///
+/// # Safety
+///
+/// No-op; exists only so the wasm module has a `_start` symbol. Left out of host
+/// builds, where it would clash with the C runtime's `_start` when linking doctests.
+#[cfg(target_arch = "wasm32")]
 #[no_mangle]
 pub unsafe extern "C" fn _start() {}
 
-#[derive(Deserialize, Clone)]
-struct PresetConfig {
-    emoji: String,
+// "znotify-plugin-version:<version>;" in a custom section of the wasm, so `znotify install-plugin`
+// can tell which version a file on disk is without loading it
+#[cfg(target_arch = "wasm32")]
+const VERSION_MARKER: &str = concat!("znotify-plugin-version:", env!("CARGO_PKG_VERSION"), ";");
+
+#[cfg(target_arch = "wasm32")]
+#[link_section = "znotify_version"]
+#[used]
+static VERSION_SECTION: [u8; VERSION_MARKER.len()] = marker_bytes();
+
+// VERSION_MARKER as an array; a link_section static can't hold a reference
+#[cfg(target_arch = "wasm32")]
+const fn marker_bytes() -> [u8; VERSION_MARKER.len()] {
+    let bytes = VERSION_MARKER.as_bytes();
+    let mut out = [0; VERSION_MARKER.len()];
+    let mut i = 0;
+    while i < bytes.len() {
+        out[i] = bytes[i];
+        i += 1;
+    }
+    out
+}
+
+// zellij-tile's shim imports this from the Zellij host. Host builds (tests, doctests, other
+// crates using `naming`) never call into Zellij, but still need the symbol to link.
+#[cfg(not(target_arch = "wasm32"))]
+#[no_mangle]
+pub extern "C" fn host_run_plugin_command() {}
+
+// How much the plugin logs; each level includes the ones before it
+#[derive(Default, Clone, Copy, PartialEq, PartialOrd, Debug)]
+enum LogLevel {
+    Off,
+    Error,
+    #[default]
+    Warn,
+    Info,
+    Debug,
+}
+
+impl std::str::FromStr for LogLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "off" => Ok(LogLevel::Off),
+            "error" => Ok(LogLevel::Error),
+            "warn" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            _ => Err(()),
+        }
+    }
+}
+
+// Events that clear the notification of a tab
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum CleanTrigger {
+    Focus,  // Switching to the tab
+    Pane,  // Any pane update while the tab is focused
+}
+
+// How a decorated tab name is laid out, from the plugin config
+struct NameStyle<'a> {
+    position: EmojiPosition,
+    separator: &'a str,
+    max_count: u32,
+}
+
+// What a notification does to a tab that already shows one
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+enum ReplaceMode {
+    Always,  // The newest notification always wins
+    #[default]
+    Priority,  // The newest wins unless the shown one has a higher priority
+    Sticky,  // The first one stays until the tab is cleared
+}
+
+// The manual tagger in the plugin pane: pick a tab, then a preset to put on it
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+struct Picker {
+    choosing_preset: bool,  // Past the tab list, now choosing what to put on it
+    tab: usize,  // Selected entry in the tab list
+    preset: usize,  // Selected entry in the preset list
+}
+
+// What a key press in the picker asks for
+#[derive(PartialEq, Debug)]
+enum PickerAction {
+    Stay,  // Keep the picker open, selection possibly moved
+    Close,
+    Apply { tab: usize, preset: usize },  // Indexes into the tab and preset lists
+}
+
+impl Picker {
+    // Move through the picker; `tabs` and `presets` are the lengths of the lists on screen
+    fn handle_key(&mut self, key: &BareKey, tabs: usize, presets: usize) -> PickerAction {
+        let len = if self.choosing_preset { presets } else { tabs };
+        let selected = if self.choosing_preset { &mut self.preset } else { &mut self.tab };
+        match key {
+            BareKey::Up | BareKey::Char('k') => *selected = selected.saturating_sub(1),
+            BareKey::Down | BareKey::Char('j') => *selected = (*selected + 1).min(len.saturating_sub(1)),
+            BareKey::Enter if len == 0 => {}
+            BareKey::Enter if self.choosing_preset => {
+                return PickerAction::Apply { tab: self.tab, preset: self.preset };
+            }
+            BareKey::Enter => self.choosing_preset = true,
+            BareKey::Esc if self.choosing_preset => self.choosing_preset = false,
+            BareKey::Esc => return PickerAction::Close,
+            _ => {}
+        }
+        PickerAction::Stay
+    }
+}
+
+// Whose tab a piece of per-tab state describes: routing by `session_name` must not let
+// one session's notification clear another's on a tab at the same position
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+struct TabKey {
+    session: String,  // The session_name arg it was routed by, else the current session ("" until known)
+    position: usize,
+}
+
+// A tab that currently carries a plugin-managed emoji; also one entry of the query reply
+#[derive(Serialize)]
+struct PendingNotification {
+    position: usize,
+    name: String,   // Tab name with the decoration removed
+    emoji: String,  // The decoration itself, e.g. "✅×2"
 }
 
 #[derive(Default)]
@@ -18,283 +282,1600 @@ struct State {
     all_tabs: Vec<TabInfo>,  // Store ALL tabs, not just the active one
     focused_tab_position: Option<usize>,  // Track which tab is currently focused
     pane_manifest: Option<PaneManifest>,  // Map panes to their tab positions
+    pane_tabs: HashMap<String, usize>,  // Pane id -> tab position, rebuilt from each PaneManifest
+    queued_messages: Vec<(PipeMessage, f64)>,  // Early pane_id notifications and their arrival time (unix secs)
+    sessions: Vec<SessionInfo>,  // All running sessions, to tell ours apart from the rest
     presets: HashMap<String, PresetConfig>,
-    debug: bool,
+    managed_emojis: Vec<String>,  // Emoji strings this plugin adds, and therefore strips (longest first)
+    managed_labels: Vec<String>,  // Preset labels, stripped together with their emoji
+    default_emoji: String,  // Shown for an empty payload
+    unknown_emoji: String,  // Shown for a payload that matches no preset
+    agent_emojis: HashMap<String, String>,  // Agent id -> emoji shown instead of the preset's, from an `agent` arg
+    pipe_name: String,  // Pipe messages we answer to; "<name>-clear" and "<name>-all" derive from it
+    position: EmojiPosition,
+    replace_mode: ReplaceMode,
+    separator: String,  // Placed between the tab name and the emoji
+    max_name_len: usize,  // Widest tab name kept in front of the emoji, in columns (0 = no limit)
+    // Per-tab state is keyed by session and position, and follows tabs that move (see follow_tab_moves)
+    full_names: HashMap<TabKey, (String, String)>,  // Full and truncated name per shortened tab
+    notification_counts: HashMap<TabKey, u32>,  // Unacknowledged notifications per tab
+    max_count: u32,  // Highest repeat count shown before the badge reads ×N+ (0 = no limit)
+    notified_at: HashMap<TabKey, f64>,  // Unix time (secs) of the last notification per tab
+    clear_after: HashMap<TabKey, u64>,  // Auto-clear timeout of the notification shown per tab
+    notification_priorities: HashMap<TabKey, u8>,  // Priority of the emoji shown per tab
+    message_labels: HashMap<TabKey, Vec<String>>,  // Labels with a `message` put on each tab since it was last cleaned
+    raw_emojis: HashMap<TabKey, Vec<String>>,  // Unmanaged `emoji` args put on each tab since it was last cleaned
+    clear_after_secs: u64,  // Auto-clear notifications older than this (0 = disabled)
+    debounce_ms: u64,  // Coalesce notifications to one tab within this window (0 = disabled)
+    cooldown_ms: u64,  // Ignore notifications to a tab this soon after it was cleared (0 = disabled)
+    cleared_at: HashMap<TabKey, f64>,  // Unix time (secs) a decorated tab was last cleared
+    pending_renames: HashMap<TabKey, (String, f64)>,  // Debounced tab name and its flush time per tab
+    clean_triggers: Vec<CleanTrigger>,  // Events that strip a tab's emojis
+    suppress_foreground: bool,  // Don't decorate the tab that's already focused
+    mute_tabs: Vec<String>,  // Tab positions or name globs that never get decorated
+    tab_position_base: usize,  // Number of the first tab in tab_position(s) args: 0 or 1
+    skip_sync_tabs: bool,  // Don't decorate a tab while its panes are synced
+    no_emoji: bool,  // ASCII indicators only: the text theme, and no color circles
+    clear_key: Option<char>,  // Clears every tab when pressed in the plugin pane (None = disabled)
+    picker_key: Option<char>,  // Opens the tab picker when pressed in the plugin pane (None = disabled)
+    picker: Option<Picker>,  // The open tab picker, if any
+    auto_command_status: bool,  // Decorate a tab when one of its command panes exits
+    command_success_emoji: String,
+    command_failure_emoji: String,
+    exited_panes: HashSet<u32>,  // Terminal panes already seen exited, so each exit notifies once
+    decorated_tabs: HashSet<TabKey>,  // Tabs carrying our decoration; no other tab is ever cleaned
+    decorations_restored: bool,  // Whether the first TabUpdate has been scanned for earlier decorations
+    can_rename: bool,  // ChangeApplicationState granted; assumed until a PermissionRequestResult says otherwise
+    log_level: LogLevel,
+    log_file: Option<File>,  // Log destination; stderr when unset
 }
 
 register_plugin!(State);
 
 impl ZellijPlugin for State {
     fn load(&mut self, configuration: BTreeMap<String, String>) {
-        // Parse debug flag from config (default: false)
-        self.debug = configuration.get("debug")
+        // Parse log level from config (default: warn); the older debug "true" means debug
+        self.log_level = log_level(&configuration);
+
+        // Parse log file from config (default: stderr)
+        if let Some(path) = configuration.get("log_file").filter(|p| !p.is_empty()) {
+            match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => self.log_file = Some(file),
+                Err(e) => {
+                    self.log(LogLevel::Warn, &format!("⚠️  Could not open log_file '{}': {}, logging to stderr", path, e));
+                }
+            }
+        }
+
+        self.log(LogLevel::Info, &format!("🚀 Plugin loaded - Version {}", VERSION));
+        self.can_rename = true;
+
+        // Parse pipe name from config (default: notify)
+        self.pipe_name = configuration.get("pipe_name")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .unwrap_or("notify")
+            .to_string();
+
+        // Parse emoji position from config (default: suffix)
+        self.position = match configuration.get("position").map(|s| s.as_str()) {
+            None | Some("suffix") => EmojiPosition::Suffix,
+            Some("prefix") => EmojiPosition::Prefix,
+            Some(other) => {
+                self.log(LogLevel::Warn, &format!("⚠️  Unknown position '{}', using suffix", other));
+                EmojiPosition::Suffix
+            }
+        };
+
+        // Parse replace mode from config (default: priority)
+        self.replace_mode = match configuration.get("replace_mode").map(|s| s.trim()) {
+            None | Some("priority") => ReplaceMode::Priority,
+            Some("always") => ReplaceMode::Always,
+            Some("sticky") => ReplaceMode::Sticky,
+            Some(other) => {
+                self.log(LogLevel::Warn, &format!("⚠️  Unknown replace_mode '{}', using priority", other));
+                ReplaceMode::Priority
+            }
+        };
+
+        // Parse separator from config (default: single space)
+        self.separator = configuration.get("separator")
+            .filter(|s| !s.is_empty())
+            .cloned()
+            .unwrap_or_else(|| naming::DEFAULT_SEPARATOR.to_string());
+
+        // Parse clean triggers from config (default: focus); clear_on_focus "false" drops focus
+        self.clean_triggers = self.parse_clean_triggers(&configuration);
+
+        // Parse foreground suppression flag from config (default: false); skip_if_focused is an alias
+        self.suppress_foreground = configuration.get("suppress_foreground")
+            .or_else(|| configuration.get("skip_if_focused"))
             .and_then(|s| s.parse().ok())
             .unwrap_or(false);
 
-        if self.debug {
-            eprintln!("[zellij-notify] 🚀 Plugin loaded - Version {}", VERSION);
-        }
+        // Parse muted tabs from config (default: none), a comma-separated list
+        self.mute_tabs = configuration.get("mute_tabs")
+            .map(|s| s.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+            .unwrap_or_default();
+
+        // Parse synced-tab flag from config (default: false)
+        self.skip_sync_tabs = configuration.get("skip_sync_tabs")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+
+        // Parse the clear-all and picker keys from config (default: c and p); an empty value disables one
+        self.clear_key = self.parse_key(&configuration, "clear_key", DEFAULT_CLEAR_KEY);
+        self.picker_key = self.parse_key(&configuration, "picker_key", DEFAULT_PICKER_KEY);
+
+        // Parse auto-clear timeout from config (default: 0, disabled)
+        self.clear_after_secs = configuration.get("clear_after_secs")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+
+        // Parse debounce window from config (default: 0, disabled)
+        self.debounce_ms = configuration.get("debounce_ms")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+
+        // Parse post-clear cooldown from config (default: 0, disabled)
+        self.cooldown_ms = configuration.get("cooldown_ms")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+
+        // Parse the numbering of tab_position(s) args from config (default: 0, the first tab is 0)
+        self.tab_position_base = match configuration.get("tab_position_base").map(|s| s.trim()) {
+            None | Some("0") => 0,
+            Some("1") => 1,
+            Some(other) => {
+                self.log(LogLevel::Warn, &format!("⚠️  tab_position_base must be 0 or 1, got '{}'; using 0", other));
+                0
+            }
+        };
+
+        // Parse the repeat count cap from config (default: 0, no limit)
+        self.max_count = configuration.get("max_count")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+
+        // Parse name length limit from config (default: 0, no limit)
+        self.max_name_len = configuration.get("max_name_len")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+
+        // Parse the ASCII-only flag from config (default: false); it implies the text theme
+        self.no_emoji = configuration.get("no_emoji")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
 
-        subscribe(&[EventType::TabUpdate, EventType::PaneUpdate]);
+        // Parse theme from config (default: emoji); it supplies every indicator not configured below
+        let theme = self.parse_theme(&configuration);
+
+        // Parse fallback emojis from config (default: the theme's, ✅ and ❓)
+        self.default_emoji = self.fallback_emoji(&configuration, "default_emoji", theme.default_emoji);
+        self.unknown_emoji = self.fallback_emoji(&configuration, "unknown_emoji", theme.unknown_emoji);
+
+        // Parse command pane status flag and emojis from config (default: off, the theme's ✅ and ❌)
+        self.auto_command_status = configuration.get("auto_command_status")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+        self.command_success_emoji = self.fallback_emoji(&configuration, "command_success_emoji", theme.command_success_emoji);
+        self.command_failure_emoji = self.fallback_emoji(&configuration, "command_failure_emoji", theme.command_failure_emoji);
+
+        subscribe(&[
+            EventType::TabUpdate,
+            EventType::PaneUpdate,
+            EventType::Timer,
+            EventType::SessionUpdate,
+            EventType::Key,
+            EventType::PermissionRequestResult,
+        ]);
         request_permission(&[
             PermissionType::ReadApplicationState,
-            PermissionType::ChangeApplicationState
+            PermissionType::ChangeApplicationState,
+            PermissionType::ReadCliPipes,
         ]);
 
-        // Parse presets from config
-        if let Some(presets_json) = configuration.get("presets") {
-            match serde_json::from_str(presets_json) {
-                Ok(presets) => {
-                    self.presets = presets;
-                    if self.debug {
-                        eprintln!("[zellij-notify] ✅ Loaded {} presets from config", self.presets.len());
-                    }
-                }
-                Err(e) => {
-                    if self.debug {
-                        eprintln!("[zellij-notify] ⚠️  Failed to parse presets: {}", e);
-                    }
-                }
-            }
+        // Parse presets from presets_file, then inline presets, which win on conflicting names.
+        // An explicit theme's table sits underneath both, so presets override single entries.
+        let theme_presets: HashMap<String, PresetConfig> = theme.presets.iter()
+            .map(|(name, emoji)| (name.to_string(), PresetConfig { emoji: emoji.to_string(), ..Default::default() }))
+            .collect();
+        let presets_file = configuration.get("presets_file").filter(|path| !path.is_empty());
+        let inline_json = configuration.get("presets");
+        if presets_file.is_none() && inline_json.is_none() {
+            // Without presets, the stock hook names still get their usual emojis
+            self.presets = theme_presets;
+            self.log(LogLevel::Info, &format!("✅ No presets configured, using {} built-in {} presets",
+                self.presets.len(), theme.name));
+        } else {
+            let explicit_theme = configuration.contains_key("theme") || self.no_emoji;
+            let mut presets = if explicit_theme { theme_presets } else { HashMap::new() };
+            presets.extend(presets_file.and_then(|path| self.read_presets_file(path)).unwrap_or_default());
+            presets.extend(inline_json.and_then(|json| self.parse_presets(json, "config")).unwrap_or_default());
+            self.add_presets(presets);
+            self.log(LogLevel::Info, &format!("✅ Loaded {} presets from config", self.presets.len()));
         }
+
+        // Parse per-agent emojis from config (default: none)
+        self.agent_emojis = configuration.get("agent_emojis")
+            .map(|json| self.parse_agent_emojis(json))
+            .unwrap_or_default();
+
+        // Build the strip set once so adding and removing emojis stay symmetric
+        let mut fallbacks = vec![self.default_emoji.as_str(), self.unknown_emoji.as_str()];
+        fallbacks.extend(self.agent_emojis.values().map(String::as_str));
+        if self.auto_command_status {
+            fallbacks.push(&self.command_success_emoji);
+            fallbacks.push(&self.command_failure_emoji);
+        }
+        self.managed_emojis = managed_emojis(&self.presets, &fallbacks);
+        self.managed_labels = managed_labels(&self.presets);
+        self.log(LogLevel::Debug, &format!("🧹 Managed emojis: {}", self.managed_emojis.join(" ")));
     }
 
     fn update(&mut self, event: Event) -> bool {
         match event {
             Event::TabUpdate(tabs) => {
-                if self.debug {
-                    eprintln!("[zellij-notify] v{}", VERSION);
-                    eprintln!("[zellij-notify] 📋 TAB UPDATE: {} tabs total", tabs.len());
-                }
+                self.log(LogLevel::Debug, &format!("v{}", VERSION));
+                self.log(LogLevel::Debug, &format!("📋 TAB UPDATE: {} tabs total", tabs.len()));
 
                 // Store ALL tabs (not just the active one)
                 self.all_tabs = tabs.clone();
 
+                // A reloaded plugin doesn't know what its previous instance decorated, so adopt
+                // tabs whose names only the plugin could have rendered, once; a plain "release ✅"
+                // may be the user's own name and later names are left alone
+                if !self.decorations_restored {
+                    self.decorations_restored = true;
+                    for tab in &tabs {
+                        if is_rendered(&tab.name, &self.managed_emojis, &self.managed_labels, &self.separator, self.position) {
+                            self.decorated_tabs.insert(self.tab_key(tab.position, None));
+                        }
+                    }
+                }
+
                 // Find the currently focused tab
-                for (idx, tab) in tabs.iter().enumerate() {
+                // Tabs are identified by `position` everywhere; their order in this
+                // Vec is not guaranteed to match it, e.g. right after a tab move
+                for tab in &tabs {
                     if tab.active {
                         let is_new_focus = self.focused_tab_position != Some(tab.position);
 
                         // Only clean emojis when first focusing on a tab (prevents loops)
                         if is_new_focus {
-                            if self.debug {
-                                eprintln!("[zellij-notify] 🎯 FOCUS: Tab {} '{}' (idx={}, previous: {:?})",
-                                    tab.position, tab.name, idx, self.focused_tab_position);
-                            }
+                            self.log(LogLevel::Debug, &format!("🎯 FOCUS: Tab {} '{}' (previous: {:?})",
+                                tab.position, tab.name, self.focused_tab_position));
 
                             self.focused_tab_position = Some(tab.position);
 
-                            // Check if this tab has emojis
-                            let cleaned = remove_trailing_emojis(&tab.name);
-                            if cleaned != tab.name {
-                                if self.debug {
-                                    eprintln!("[zellij-notify] 🔄 CLEAN: '{}' → '{}'", tab.name, cleaned);
-                                }
-
-                                // Zellij uses 1-based indexing, tab.position is 0-based
-                                let tab_index = tab.position as u32 + 1;
-                                rename_tab(tab_index, cleaned);
+                            // Leave the emoji in place until cleared some other way
+                            if self.clean_triggers.contains(&CleanTrigger::Focus) {
+                                self.clean_tab(&self.tab_key(tab.position, None));
                             }
                         }
                         break;
                     }
                 }
-                false
+                self.replay_queued_messages();
+                true // Re-render the notification list
             }
             Event::PaneUpdate(pane_manifest) => {
-                if self.debug {
-                    eprintln!("[zellij-notify] 🗂️  PANE UPDATE: Received PaneManifest");
-                    eprintln!("[zellij-notify]   Number of tabs with panes: {}", pane_manifest.panes.len());
+                self.log(LogLevel::Debug, "🗂️  PANE UPDATE: Received PaneManifest");
+                self.log(LogLevel::Debug, &format!("  Number of tabs with panes: {}", pane_manifest.panes.len()));
+
+                if self.auto_command_status {
+                    self.notify_exited_commands(&pane_manifest);
+                }
+
+                // Closing or moving a tab shifts the positions of the tabs after it
+                if let Some(previous) = self.pane_manifest.take() {
+                    self.follow_tab_moves(&tab_moves(&previous, &pane_manifest));
                 }
 
                 // Store the pane manifest so we can map pane IDs to tabs
+                self.pane_tabs = pane_index(&pane_manifest);
                 self.pane_manifest = Some(pane_manifest);
+
+                // Activity in the focused tab means you're working there again
+                if self.clean_triggers.contains(&CleanTrigger::Pane) {
+                    if let Some(position) = self.focused_tab_position {
+                        self.clean_tab(&self.tab_key(position, None));
+                    }
+                }
+                self.replay_queued_messages();
                 false
             }
+            Event::SessionUpdate(sessions, _resurrectable) => {
+                self.log(LogLevel::Debug, &format!("🌐 SESSION UPDATE: {} sessions (current: {:?})",
+                    sessions.len(), sessions.iter().find(|s| s.is_current_session).map(|s| &s.name)));
+
+                // State recorded before we knew our session's name, or under its old name, is ours
+                let previous = self.current_session().to_string();
+                self.sessions = sessions;
+                let current = self.current_session().to_string();
+                if current != previous {
+                    self.rekey(|key| Some(if key.session == previous {
+                        TabKey { session: current.clone(), ..key.clone() }
+                    } else {
+                        key.clone()
+                    }));
+                }
+                false
+            }
+            Event::Timer(_elapsed) => {
+                self.flush_pending_renames();
+                self.clear_expired_notifications();
+                false
+            }
+            Event::PermissionRequestResult(status) => {
+                self.can_rename = status == PermissionStatus::Granted;
+                if self.can_rename {
+                    self.log(LogLevel::Debug, "🔓 Permissions granted");
+                } else {
+                    self.log(LogLevel::Error, "🔒 Permissions denied: tabs can't be renamed, so notifications won't show");
+                }
+                true
+            }
+            // Key events only arrive while the plugin pane itself is focused
+            Event::Key(key) => {
+                if !key.key_modifiers.is_empty() {
+                    return false;
+                }
+                if self.picker.is_some() {
+                    self.picker_key_pressed(&key.bare_key);
+                    return true;
+                }
+                match key.bare_key {
+                    BareKey::Char(c) if Some(c) == self.clear_key => {
+                        self.clear_all();
+                        true
+                    }
+                    BareKey::Char(c) if Some(c) == self.picker_key => {
+                        self.picker = Some(Picker::default());
+                        true
+                    }
+                    _ => false,
+                }
+            }
             _ => false
         }
     }
 
-    fn render(&mut self, _rows: usize, _cols: usize) {}
+    fn render(&mut self, rows: usize, cols: usize) {
+        if rows == 0 || cols == 0 {
+            return;
+        }
+
+        if !self.can_rename {
+            for line in [
+                "Permission to change tabs was denied, so znotify can't rename them.",
+                "Remove zellij-notify from ~/.cache/zellij/permissions.kdl and reload the plugin to be asked again.",
+            ].iter().take(rows) {
+                println!("{}", truncate(line, cols));
+            }
+            return;
+        }
+
+        if let Some(picker) = self.picker {
+            self.render_picker(&picker, rows, cols);
+            return;
+        }
+
+        let pending = self.pending_notifications();
+        if pending.is_empty() {
+            println!("{}", truncate("No notifications", cols));
+            return;
+        }
+
+        // Leave the last row for a "more" line when the list doesn't fit
+        let visible = if pending.len() > rows { rows.saturating_sub(1) } else { pending.len() };
+        for notification in pending.iter().take(visible) {
+            let line = format!("{} {} {}", notification.position, notification.emoji, notification.name);
+            println!("{}", truncate(&line, cols));
+        }
+        if visible < pending.len() {
+            println!("{}", truncate(&format!("… {} more", pending.len() - visible), cols));
+        }
+    }
 
     fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
-        // Only handle "notify" commands
-        if pipe_message.name != "notify" {
+        let clear_name = format!("{}-clear", self.pipe_name);
+        let all_name = format!("{}-all", self.pipe_name);
+        let query_name = format!("{}-query", self.pipe_name);
+        let goto_name = format!("{}-goto", self.pipe_name);
+
+        if pipe_message.name == clear_name {
+            self.clear_notification(&pipe_message);
             return false;
         }
 
-        if self.debug {
-            eprintln!("[zellij-notify] 📨 PIPE received!");
-            eprintln!("[zellij-notify]   Name: {}", pipe_message.name);
-            eprintln!("[zellij-notify]   Payload: {:?}", pipe_message.payload);
-            eprintln!("[zellij-notify]   Source: {:?}", pipe_message.source);
-            eprintln!("[zellij-notify]   Args: {:?}", pipe_message.args);
-            eprintln!("[zellij-notify]   Is Private: {}", pipe_message.is_private);
+        if pipe_message.name == goto_name {
+            self.goto_notification(&pipe_message);
+            return false;
+        }
 
-            // Log session_name and tab_name if provided
-            if let Some(session_name) = pipe_message.args.get("session_name") {
-                eprintln!("[zellij-notify]   Session name: {}", session_name);
-            }
-            if let Some(tab_name) = pipe_message.args.get("tab_name") {
-                eprintln!("[zellij-notify]   Tab name: {}", tab_name);
-            }
+        if pipe_message.name == query_name {
+            self.answer_query(&pipe_message);
+            return false;
+        }
 
-            eprintln!("[zellij-notify]   Currently focused tab: {:?}", self.focused_tab_position);
-            eprintln!("[zellij-notify]   All tabs at pipe time:");
-            for tab in &self.all_tabs {
-                eprintln!("[zellij-notify]     - Tab {}: '{}' (active={}, is_sync_panes_active={})",
-                    tab.position, tab.name, tab.active, tab.is_sync_panes_active);
-            }
+        // Only handle our own pipe; "<pipe_name>-all" is shorthand for target=all
+        if pipe_message.name != self.pipe_name && pipe_message.name != all_name {
+            return false;
+        }
+
+        self.log(LogLevel::Debug, "📨 PIPE received!");
+        self.log(LogLevel::Debug, &format!("  Name: {}", pipe_message.name));
+        self.log(LogLevel::Debug, &format!("  Payload: {:?}", pipe_message.payload));
+        self.log(LogLevel::Debug, &format!("  Source: {:?}", pipe_message.source));
+        self.log(LogLevel::Debug, &format!("  Args: {:?}", pipe_message.args));
+        self.log(LogLevel::Debug, &format!("  Is Private: {}", pipe_message.is_private));
+
+        // Log session_name and tab_name if provided
+        if let Some(session_name) = pipe_message.args.get("session_name") {
+            self.log(LogLevel::Debug, &format!("  Session name: {}", session_name));
+        }
+        if let Some(tab_name) = pipe_message.args.get("tab_name") {
+            self.log(LogLevel::Debug, &format!("  Tab name: {}", tab_name));
+        }
+
+        self.log(LogLevel::Debug, &format!("  Currently focused tab: {:?}", self.focused_tab_position));
+        self.log(LogLevel::Debug, "  All tabs at pipe time:");
+        for tab in &self.all_tabs {
+            self.log(LogLevel::Debug, &format!("    - Tab {}: '{}' (active={}, is_sync_panes_active={})",
+                tab.position, tab.name, tab.active, tab.is_sync_panes_active));
         }
 
-        // Get preset based on payload (positional argument)
-        let preset = match pipe_message.payload.as_deref() {
-            None | Some("") => {
-                if self.debug {
-                    eprintln!("[zellij-notify] ✅ Using default preset");
+        // A raw `emoji` arg wins over the payload's preset lookup
+        let mut preset = if let Some(emoji) = pipe_message.args.get("emoji").filter(|e| !e.is_empty()) {
+            self.log(LogLevel::Debug, &format!("🎨 Using raw emoji: {}", emoji));
+            PresetConfig { emoji: emoji.clone(), ..Default::default() }
+        } else {
+            // Get preset based on payload (positional argument)
+            match pipe_message.payload.as_deref() {
+                None | Some("") => {
+                    self.log(LogLevel::Debug, "✅ Using default preset");
+                    PresetConfig { emoji: self.default_emoji.clone(), ..Default::default() }
                 }
-                PresetConfig { emoji: "✅".to_string() }
-            }
-            Some(key) => {
-                match self.presets.get(key) {
-                    Some(preset) => {
-                        if self.debug {
-                            eprintln!("[zellij-notify] 📦 Using preset '{}': {}", key, preset.emoji);
+                Some(key) => {
+                    match self.presets.get(key) {
+                        Some(preset) => {
+                            self.log(LogLevel::Debug, &format!("📦 Using preset '{}': {}", key, preset.emoji));
+                            preset.clone()
                         }
-                        preset.clone()
-                    }
-                    None => {
-                        if self.debug {
-                            eprintln!("[zellij-notify] ❓ Unknown preset '{}', using fallback", key);
+                        None if self.presets.is_empty() => {
+                            self.log(LogLevel::Warn, &format!("⚠️  No presets are configured, using fallback for '{}'", key));
+                            PresetConfig { emoji: self.unknown_emoji.clone(), ..Default::default() }
+                        }
+                        None => {
+                            self.log(LogLevel::Debug, &format!("❓ Unknown preset '{}', using fallback", key));
+                            PresetConfig { emoji: self.unknown_emoji.clone(), ..Default::default() }
                         }
-                        PresetConfig { emoji: "❓".to_string() }
                     }
                 }
             }
         };
 
-        let emoji = &preset.emoji;
-
-        // Try to identify which tab sent the pipe command
-        // Method 1: Check if pane_id was passed via args (from shell wrapper)
-        let target_tab_position = if let Some(pane_id) = pipe_message.args.get("pane_id") {
-            if self.debug {
-                eprintln!("[zellij-notify] 🆔 Pane ID provided: {}", pane_id);
-            }
-
-            // Use PaneManifest to find which tab contains this pane
-            if let Some(ref manifest) = self.pane_manifest {
-                // PaneManifest.panes is a BTreeMap<usize, Vec<PaneInfo>>
-                // where the key is the tab position (0-indexed)
-                let mut found_tab: Option<usize> = None;
-                for (tab_position, panes) in &manifest.panes {
-                    // Check if any pane in this tab matches our pane_id
-                    for pane in panes {
-                        if pane.id.to_string() == *pane_id {
-                            found_tab = Some(*tab_position);
-                            if self.debug {
-                                eprintln!("[zellij-notify] ✅ Found pane {} in tab {}", pane_id, tab_position);
-                            }
-                            break;
-                        }
-                    }
-                    if found_tab.is_some() {
-                        break;
-                    }
-                }
+        // An `agent` arg with its own emoji swaps it in for the preset's, keeping the rest
+        // of the preset; a raw `emoji` arg still wins
+        if pipe_message.args.get("emoji").is_none_or(|e| e.is_empty()) {
+            let agent = pipe_message.args.get("agent").map(|a| a.trim());
+            if let Some((agent, emoji)) = agent.and_then(|a| self.agent_emojis.get(a).map(|e| (a, e))) {
+                self.log(LogLevel::Debug, &format!("🤖 Using emoji of agent '{}': {}", agent, emoji));
+                preset.emoji = emoji.clone();
+                preset.foreground_emoji = None;
+                preset.background_emoji = None;
+            }
+        }
+
+        // A `message` arg rides along after the emoji (and any preset label); the tab it
+        // lands on remembers it, so cleaning that tab strips it together with the emoji
+        if let Some(message) = pipe_message.args.get("message").map(|m| clean_message(m)).filter(|m| !m.is_empty()) {
+            let label = match preset.label.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+                Some(label) => format!("{} {}", label, message),
+                None => message,
+            };
+            self.log(LogLevel::Debug, &format!("💬 Message: {}", label));
+            preset.label = Some(label);
+        }
 
-                if found_tab.is_none() && self.debug {
-                    eprintln!("[zellij-notify] ⚠️  Pane ID {} not found in PaneManifest", pane_id);
+        // A `set_name` arg renames the tab in the same step, so a script doesn't race its
+        // own separate rename against the notification
+        let mut base_name = pipe_message.args.get("set_name").map(|n| n.trim()).filter(|n| !n.is_empty());
+
+        let session_name = pipe_message.args.get("session_name").map(|s| s.as_str());
+
+        let broadcast = pipe_message.name == all_name
+            || pipe_message.args.get("target").map(|s| s.as_str()) == Some("all");
+        let several = broadcast || pipe_message.args.contains_key("tab_positions");
+        if several && base_name.take().is_some() {
+            self.log(LogLevel::Warn, "⚠️  Ignoring set_name: it would give every targeted tab the same name");
+        }
+        if broadcast {
+            if !self.session_in_scope(session_name) {
+                return false;
+            }
+            let skip_active = pipe_message.args.get("skip_active")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false);
+            for position in self.broadcast_positions(skip_active) {
+                self.notify_tab(position, &preset, None, session_name);
+            }
+            return false;
+        }
+
+        // An explicit list of tabs, narrower than a broadcast
+        if let Some(list) = pipe_message.args.get("tab_positions") {
+            if !self.session_in_scope(session_name) {
+                return false;
+            }
+            for position in self.listed_positions(list) {
+                self.notify_tab(position, &preset, None, session_name);
+            }
+            return false;
+        }
+
+        // Session startup: hooks can fire before Zellij has told us about tabs and panes
+        if pipe_message.args.contains_key("pane_id") && (self.pane_manifest.is_none() || self.all_tabs.is_empty()) {
+            self.queue_message(pipe_message.clone());
+            return false;
+        }
+
+        let target_tab_position = self.resolve_target_tab(&pipe_message.args);
+
+        // Update the identified tab
+        if let Some(position) = target_tab_position {
+            self.notify_tab(position, &preset, base_name, session_name);
+        } else {
+            self.log(LogLevel::Warn, "⚠️  Could not identify target tab");
+        }
+
+        false // No UI re-render needed
+    }
+}
+
+impl State {
+    // Write a line to log_file, or stderr when there is none, if `level` is enabled
+    fn log(&self, level: LogLevel, message: &str) {
+        if level > self.log_level {
+            return;
+        }
+        if let Some(mut file) = self.log_file.as_ref() {
+            if writeln!(file, "[zellij-notify] {}", message).is_ok() {
+                return;
+            }
+        }
+        eprintln!("[zellij-notify] {}", message);
+    }
+
+    // Decorate one tab with a preset's emoji, bumping its repeat count. `base_name` (a
+    // `set_name` arg) replaces the tab's current name instead of keeping it.
+    fn notify_tab(&mut self, position: usize, preset: &PresetConfig, base_name: Option<&str>, session_name: Option<&str>) {
+        if !self.can_rename {
+            self.log(LogLevel::Warn, &format!("⚠️  Not notifying tab {}: permission to rename tabs was denied", position));
+            return;
+        }
+
+        let key = self.tab_key(position, session_name);
+
+        // A late event right after the tab was acknowledged would only re-flash it
+        if self.in_cooldown(&key) {
+            self.log(LogLevel::Debug, &format!("🧊 Tab {} was cleared less than {}ms ago, ignoring", position, self.cooldown_ms));
+            return;
+        }
+
+        if let Some(tab) = self.all_tabs.iter().find(|t| t.position == position) {
+            if is_muted(&self.mute_tabs, position, &self.clean_name(&key, &tab.name)) {
+                self.log(LogLevel::Debug, &format!("🔇 Tab {} '{}' is muted", position, tab.name));
+                return;
+            }
+            // Input goes to every pane at once there, so one pane finishing says little
+            if self.skip_sync_tabs && tab.is_sync_panes_active {
+                self.log(LogLevel::Debug, &format!("🔗 Tab {} '{}' has synced panes, skipping", position, tab.name));
+                return;
+            }
+        }
+
+        // The focused tab can get a quieter emoji, or none at all
+        let focused = self.focused_tab_position == Some(position);
+        if focused && self.suppress_foreground {
+            self.log(LogLevel::Debug, &format!("🔕 Tab {} is focused, suppressing notification", position));
+            return;
+        }
+        let variant = if focused { &preset.foreground_emoji } else { &preset.background_emoji };
+        let emoji = variant.as_ref().unwrap_or(&preset.emoji);
+
+        // Whether a new notification may replace the one on the tab depends on replace_mode
+        let priority = preset.priority.unwrap_or(0);
+        let shown = self.notification_priorities.get(&key).copied().unwrap_or(0);
+        match self.replace_mode {
+            ReplaceMode::Always => {}
+            ReplaceMode::Priority if priority < shown => {
+                self.log(LogLevel::Debug, &format!("🔕 Keeping tab {}: priority {} < {}", position, priority, shown));
+                return;
+            }
+            ReplaceMode::Priority => {}
+            ReplaceMode::Sticky if self.decorated_tabs.contains(&key) => {
+                self.log(LogLevel::Debug, &format!("📌 Keeping tab {}: sticky until cleared", position));
+                return;
+            }
+            ReplaceMode::Sticky => {}
+        }
+
+        if let Some(tab) = self.all_tabs.iter().find(|t| t.position == position) {
+            // Only strip a previous decoration of ours; an undecorated name is kept whole
+            let cleaned_name = if let Some(name) = base_name {
+                // Whatever full name was kept for the old one no longer applies
+                self.full_names.remove(&key);
+                name.to_string()
+            } else if self.decorated_tabs.contains(&key) {
+                self.clean_name(&key, &tab.name)
+            } else {
+                tab.name.clone()
+            };
+            // A base that was nothing but our emoji shows as the name the clean would give back
+            let cleaned_name = non_empty_name(position, cleaned_name);
+
+            // Shorten long names so the indicator stays visible; the full name is restored on clean
+            let cleaned_name = if self.max_name_len > 0 {
+                let short = truncate(&cleaned_name, self.max_name_len);
+                if short != cleaned_name {
+                    self.full_names.insert(key.clone(), (cleaned_name, short.clone()));
                 }
+                short
+            } else {
+                cleaned_name
+            };
+            // Past the cap the count stays at max_count + 1, which is all the badge needs
+            let count = self.notification_counts.entry(key.clone()).or_insert(0);
+            if self.max_count == 0 || *count <= self.max_count {
+                *count += 1;
+            }
+            let style = NameStyle { position: self.position, separator: &self.separator, max_count: self.max_count };
+            let new_name = render_name(&cleaned_name, emoji, *count, preset.label.as_deref(), &style);
+
+            self.log(LogLevel::Info, &format!("📝 Renaming tab {}: '{}' → '{}'",
+                tab.position, tab.name, new_name));
 
-                found_tab
+            // Summary log: TAB_NAME in SESSION_NAME EMOJI
+            self.log(LogLevel::Debug, &format!("📍 {} in {} {}",
+                cleaned_name, session_name.unwrap_or("unknown"), emoji));
+
+            // Renaming to the current name would only echo back as another TabUpdate
+            if new_name == tab.name {
+                self.log(LogLevel::Debug, &format!("⏭️  Tab {} already reads '{}', not renaming", position, new_name));
+                self.pending_renames.remove(&key);
             } else {
-                if self.debug {
-                    eprintln!("[zellij-notify] ⚠️  No PaneManifest available yet");
+                self.rename_or_defer(&key, new_name);
+            }
+            self.decorated_tabs.insert(key.clone());
+            self.notification_priorities.insert(key.clone(), priority);
+            // A raw emoji is stripped again from this tab only
+            if !self.managed_emojis.iter().any(|e| e == emoji) {
+                let emojis = self.raw_emojis.entry(key.clone()).or_default();
+                if !emojis.iter().any(|e| e == emoji) {
+                    emojis.push(emoji.to_string());
+                    sort_longest_first(emojis);
                 }
-                None
             }
-        } else if let Some(pos_str) = pipe_message.args.get("tab_position") {
-            // Method 2: Check if tab position was explicitly passed via args
-            if self.debug {
-                eprintln!("[zellij-notify] 🎯 Tab position explicitly provided: {}", pos_str);
+            if let Some(label) = preset.label.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+                let labels = self.message_labels.entry(key.clone()).or_default();
+                if !self.managed_labels.iter().any(|l| l.trim() == label) && !labels.iter().any(|l| l == label) {
+                    labels.push(label.to_string());
+                    sort_labels(labels);
+                }
+            }
+
+            // Schedule an auto-clear check for when this notification expires; the preset's
+            // own timeout replaces the global one, and whatever the tab showed before
+            let lifetime = preset.clear_after_secs.unwrap_or(self.clear_after_secs);
+            if lifetime > 0 {
+                self.notified_at.insert(key.clone(), now_secs());
+                self.clear_after.insert(key, lifetime);
+                set_timeout(lifetime as f64);
+            } else {
+                self.notified_at.remove(&key);
+                self.clear_after.remove(&key);
             }
-            pos_str.parse::<usize>().ok()
         } else {
-            // Method 3: Fall back to the currently active tab from our stored state
-            // This is NOT reliable for background commands but works for immediate commands
-            let active_tab = self.all_tabs.iter().find(|t| t.active);
-            if self.debug {
-                if let Some(tab) = active_tab {
-                    eprintln!("[zellij-notify] 🎯 Using active tab from state: {} '{}'",
-                        tab.position, tab.name);
-                } else {
-                    eprintln!("[zellij-notify] ⚠️  No active tab found in state");
-                }
+            self.log(LogLevel::Warn, &format!("⚠️  Tab at position {} not found in stored tabs", position));
+        }
+    }
+
+    // Decorate the tab of every command pane that exited since the last PaneManifest.
+    // CommandPaneExited only reports panes this plugin opened itself, so the manifest's
+    // `exited`/`exit_status` fields are the way to see the user's own command panes.
+    fn notify_exited_commands(&mut self, manifest: &PaneManifest) {
+        let mut exited = Vec::new();
+        for (tab_position, panes) in &manifest.panes {
+            for pane in panes.iter().filter(|p| !p.is_plugin && p.exited) {
+                exited.push((pane.id, *tab_position, pane.exit_status));
+            }
+        }
+
+        // Panes that were already exited before the first manifest aren't news
+        let first_manifest = self.pane_manifest.is_none();
+        let previous = std::mem::replace(
+            &mut self.exited_panes,
+            exited.iter().map(|(id, _, _)| *id).collect(),
+        );
+        if first_manifest {
+            return;
+        }
+
+        for (id, tab_position, exit_status) in exited {
+            if previous.contains(&id) {
+                continue;
+            }
+            let emoji = command_status_emoji(exit_status, &self.command_success_emoji, &self.command_failure_emoji);
+            self.log(LogLevel::Info, &format!("🏁 Command pane {} in tab {} exited with {:?}", id, tab_position, exit_status));
+            let preset = PresetConfig { emoji: emoji.to_string(), ..Default::default() };
+            self.notify_tab(tab_position, &preset, None, None);
+        }
+    }
+
+    // Tab positions a broadcast notification should rename, capped at MAX_BROADCAST_TABS
+    fn broadcast_positions(&self, skip_active: bool) -> Vec<usize> {
+        let mut positions: Vec<usize> = self.all_tabs.iter()
+            .filter(|t| !(skip_active && Some(t.position) == self.focused_tab_position))
+            .map(|t| t.position)
+            .collect();
+
+        if positions.len() > MAX_BROADCAST_TABS {
+            self.log(LogLevel::Warn, &format!("⚠️  Broadcast to {} tabs capped at {}",
+                positions.len(), MAX_BROADCAST_TABS));
+            positions.truncate(MAX_BROADCAST_TABS);
+        }
+
+        self.log(LogLevel::Info, &format!("📣 Broadcasting to tabs {:?}", positions));
+        positions
+    }
+
+    // Tab positions from a `tab_positions` arg, warning about entries that aren't one
+    fn listed_positions(&self, list: &str) -> Vec<usize> {
+        let (mut positions, invalid) = parse_positions(list, self.tab_position_base);
+        for entry in invalid {
+            self.log(LogLevel::Warn, &format!("⚠️  Ignoring '{}' in tab_positions: not a tab position", entry));
+        }
+
+        if positions.len() > MAX_BROADCAST_TABS {
+            self.log(LogLevel::Warn, &format!("⚠️  tab_positions lists {} tabs, capped at {}",
+                positions.len(), MAX_BROADCAST_TABS));
+            positions.truncate(MAX_BROADCAST_TABS);
+        }
+
+        self.log(LogLevel::Info, &format!("📣 Notifying tabs {:?}", positions));
+        positions
+    }
+
+    // Hold a notification back until its pane can be found, dropping the oldest when full
+    fn queue_message(&mut self, pipe_message: PipeMessage) {
+        if self.queued_messages.len() >= MAX_QUEUED_MESSAGES {
+            let (dropped, _) = self.queued_messages.remove(0);
+            self.log(LogLevel::Warn, &format!("⚠️  Too many notifications before the first PaneManifest, dropping {:?}",
+                dropped.payload));
+        }
+        self.log(LogLevel::Info, &format!("⏳ No tabs or PaneManifest yet, queueing {:?} for pane {}",
+            pipe_message.payload, pipe_message.args.get("pane_id").map(String::as_str).unwrap_or("?")));
+        self.queued_messages.push((pipe_message, now_secs()));
+    }
+
+    // Send queued notifications through `pipe` again once tabs and panes are known
+    fn replay_queued_messages(&mut self) {
+        if self.queued_messages.is_empty() || self.pane_manifest.is_none() || self.all_tabs.is_empty() {
+            return;
+        }
+        let now = now_secs();
+        for (pipe_message, queued_at) in std::mem::take(&mut self.queued_messages) {
+            if now - queued_at > QUEUED_MESSAGE_MAX_AGE_SECS {
+                self.log(LogLevel::Warn, &format!("⚠️  Dropping {:?}: queued {:.0}s ago, too old to show",
+                    pipe_message.payload, now - queued_at));
+                continue;
             }
-            active_tab.map(|t| t.position)
+            self.log(LogLevel::Info, &format!("▶️  Replaying queued {:?}", pipe_message.payload));
+            self.pipe(pipe_message);
+        }
+    }
+
+    // Identify which tab a pipe message is about; see target::resolve_target for the precedence
+    fn resolve_target_tab(&self, args: &BTreeMap<String, String>) -> Option<usize> {
+        // Tabs in other sessions can't be renamed from this plugin instance
+        if !self.session_in_scope(args.get("session_name").map(|s| s.as_str())) {
+            return None;
+        }
+
+        let session_name = args.get("session_name").map(|s| s.as_str());
+        let clean_name = |position: usize, name: &str| self.clean_name(&self.tab_key(position, session_name), name);
+        let log = |level: LogLevel, message: &str| self.log(level, message);
+        let view = TargetView {
+            all_tabs: &self.all_tabs,
+            pane_manifest: self.pane_manifest.as_ref(),
+            pane_tabs: &self.pane_tabs,
+            tab_position_base: self.tab_position_base,
+            clean_name: &clean_name,
+            log: &log,
         };
+        resolve_target(args, &view)
+    }
 
-        // Update the identified tab
-        if let Some(position) = target_tab_position {
-            if let Some(tab) = self.all_tabs.iter().find(|t| t.position == position) {
-                let cleaned_name = remove_trailing_emojis(&tab.name);
-                let new_name = format!("{} {}", cleaned_name, emoji);
-
-                if self.debug {
-                    eprintln!("[zellij-notify] 📝 Renaming tab {}: '{}' → '{}'",
-                        tab.position, tab.name, new_name);
-
-                    // Summary log: TAB_NAME in SESSION_NAME EMOJI
-                    let session_name = pipe_message.args.get("session_name")
-                        .map(|s| s.as_str())
-                        .unwrap_or("unknown");
-                    eprintln!("[zellij-notify] 📍 {} in {} {}",
-                        cleaned_name, session_name, emoji);
+    // Whether a message addressed to `session_name` belongs to the session this plugin runs in
+    fn session_in_scope(&self, session_name: Option<&str>) -> bool {
+        let Some(target) = session_name.filter(|s| !s.is_empty()) else {
+            return true;
+        };
+
+        // Before the first SessionUpdate we can't tell, so assume it's ours
+        let Some(current) = self.sessions.iter().find(|s| s.is_current_session) else {
+            return true;
+        };
+
+        if current.name == target {
+            return true;
+        }
+
+        if self.sessions.iter().any(|s| s.name == target) {
+            self.log(LogLevel::Debug, &format!("🚫 Session '{}' is out of scope for this plugin (running in '{}')",
+                target, current.name));
+        } else {
+            self.log(LogLevel::Debug, &format!("🚫 Unknown session '{}' (running in '{}')", target, current.name));
+        }
+        false
+    }
+
+    // Handle a "<pipe_name>-clear" pipe: strip the emoji from the identified tab,
+    // or with an `emoji` arg only that one
+    fn clear_notification(&mut self, pipe_message: &PipeMessage) {
+        self.log(LogLevel::Info, &format!("🧽 CLEAR PIPE received! Args: {:?}", pipe_message.args));
+
+        let Some(position) = self.resolve_target_tab(&pipe_message.args) else {
+            self.log(LogLevel::Warn, "⚠️  Could not identify target tab");
+            return;
+        };
+
+        if !self.all_tabs.iter().any(|t| t.position == position) {
+            self.log(LogLevel::Warn, &format!("⚠️  Tab at position {} not found in stored tabs", position));
+            return;
+        }
+
+        let key = self.tab_key(position, pipe_message.args.get("session_name").map(|s| s.as_str()));
+        match pipe_message.args.get("emoji").map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            Some(emoji) => self.clean_emoji(&key, emoji),
+            None => self.clean_tab(&key),
+        }
+    }
+
+    // Handle a "<pipe_name>-goto" pipe: focus the identified tab and strip its emoji,
+    // without waiting for the focus to come back as a TabUpdate
+    fn goto_notification(&mut self, pipe_message: &PipeMessage) {
+        self.log(LogLevel::Info, &format!("🦘 GOTO PIPE received! Args: {:?}", pipe_message.args));
+
+        let Some(position) = self.resolve_target_tab(&pipe_message.args) else {
+            self.log(LogLevel::Warn, "⚠️  Could not identify target tab");
+            return;
+        };
+
+        if !self.all_tabs.iter().any(|t| t.position == position) {
+            self.log(LogLevel::Warn, &format!("⚠️  Tab at position {} not found in stored tabs", position));
+            return;
+        }
+
+        host::switch_to_tab_at(position);
+        self.clean_tab(&self.tab_key(position, pipe_message.args.get("session_name").map(|s| s.as_str())));
+    }
+
+    // Handle a "<pipe_name>-query" pipe: reply to the CLI with the pending notifications as JSON
+    fn answer_query(&self, pipe_message: &PipeMessage) {
+        let PipeSource::Cli(pipe_id) = &pipe_message.source else {
+            self.log(LogLevel::Warn, "⚠️  Query did not come from the CLI, nowhere to reply");
+            return;
+        };
+
+        let reply = query_reply(&self.pending_notifications());
+        self.log(LogLevel::Debug, &format!("🔎 QUERY: {}", reply));
+        cli_pipe_output(pipe_id, &format!("{}\n", reply));
+        unblock_cli_pipe_input(pipe_id);
+    }
+
+    // Tabs whose name currently carries a managed emoji, in tab order
+    fn pending_notifications(&self) -> Vec<PendingNotification> {
+        let mut pending: Vec<PendingNotification> = self.all_tabs.iter()
+            .filter(|tab| self.decorated_tabs.contains(&self.tab_key(tab.position, None)))
+            .filter_map(|tab| {
+                let cleaned = self.clean_name(&self.tab_key(tab.position, None), &tab.name);
+                if cleaned == tab.name {
+                    return None;
+                }
+                // Whatever the cleaning removed is the decoration
+                let emoji = match self.position {
+                    EmojiPosition::Suffix => tab.name.strip_prefix(cleaned.as_str()),
+                    EmojiPosition::Prefix => tab.name.strip_suffix(cleaned.as_str()),
                 }
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+                Some(PendingNotification { position: tab.position, name: cleaned, emoji })
+            })
+            .collect();
+        pending.sort_by_key(|n| n.position);
+        pending
+    }
 
-                // Zellij uses 1-based indexing, position is 0-based
-                let tab_index = position as u32 + 1;
-                rename_tab(tab_index, new_name);
-            } else {
-                if self.debug {
-                    eprintln!("[zellij-notify] ⚠️  Tab at position {} not found in stored tabs", position);
+    fn parse_clean_triggers(&self, configuration: &BTreeMap<String, String>) -> Vec<CleanTrigger> {
+        let mut triggers = Vec::new();
+        let list = configuration.get("clean_triggers").map(|s| s.as_str()).unwrap_or("focus");
+        for name in list.split(',').map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            match name {
+                "focus" => triggers.push(CleanTrigger::Focus),
+                "pane" => triggers.push(CleanTrigger::Pane),
+                other => self.log(LogLevel::Warn, &format!("⚠️  Unknown clean trigger '{}'", other)),
+            }
+        }
+
+        let clear_on_focus = configuration.get("clear_on_focus")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(true);
+        if !clear_on_focus {
+            triggers.retain(|t| *t != CleanTrigger::Focus);
+        }
+        triggers
+    }
+
+    fn read_presets_file(&self, path: &str) -> Option<HashMap<String, PresetConfig>> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => self.parse_presets(&json, path),
+            Err(e) => {
+                self.log(LogLevel::Warn, &format!("⚠️  Could not read presets_file '{}': {}", path, e));
+                None
+            }
+        }
+    }
+
+    fn parse_presets(&self, json: &str, source: &str) -> Option<HashMap<String, PresetConfig>> {
+        match preset::parse(json) {
+            Ok(presets) => Some(presets),
+            Err(e) => {
+                self.log(LogLevel::Error, &format!("⚠️  Failed to parse presets from {}: {}", source, e));
+                None
+            }
+        }
+    }
+
+    // Agent id -> emoji, skipping entries whose emoji would garble the tab name
+    fn parse_agent_emojis(&self, json: &str) -> HashMap<String, String> {
+        let agents: HashMap<String, String> = match serde_json::from_str(json) {
+            Ok(agents) => agents,
+            Err(e) => {
+                self.log(LogLevel::Error, &format!("⚠️  Failed to parse agent_emojis: {}", e));
+                return HashMap::new();
+            }
+        };
+        agents.into_iter()
+            .filter(|(agent, emoji)| match invalid_emoji_reason(emoji) {
+                Some(reason) => {
+                    self.log(LogLevel::Warn, &format!("⚠️  Skipping agent '{}': emoji {}", agent, reason));
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    // Keep the presets whose emoji won't garble the tab name
+    fn add_presets(&mut self, presets: HashMap<String, PresetConfig>) {
+        for (name, mut preset) in presets {
+            let problems = preset::problems(&preset);
+            if let Some(Problem::Emoji(reason)) = problems.iter().find(|p| p.skips_preset()) {
+                self.log(LogLevel::Warn, &format!("⚠️  Skipping preset '{}': emoji {}", name, reason));
+                continue;
+            }
+            for problem in problems {
+                match problem {
+                    // A bad focus variant just falls back to the main emoji
+                    Problem::Variant(field, reason) => {
+                        self.log(LogLevel::Warn, &format!("⚠️  Ignoring {} of preset '{}': emoji {}", field, name, reason));
+                        match field {
+                            "foreground_emoji" => preset.foreground_emoji = None,
+                            _ => preset.background_emoji = None,
+                        }
+                    }
+                    Problem::Color(color) if !self.no_emoji => {
+                        self.log(LogLevel::Warn, &format!("⚠️  Ignoring unknown color '{}' of preset '{}'", color, name));
+                    }
+                    _ => {}
                 }
             }
+            // Color circles are emoji too
+            if let Some(marker) = preset.color.as_deref().filter(|_| !self.no_emoji).and_then(color_marker) {
+                preset.emoji = with_marker(&preset.emoji, marker);
+                for variant in [&mut preset.foreground_emoji, &mut preset.background_emoji] {
+                    if let Some(emoji) = variant.as_mut() {
+                        *emoji = with_marker(emoji, marker);
+                    }
+                }
+            }
+            self.presets.insert(name, preset);
+        }
+    }
+
+    fn parse_theme(&self, configuration: &BTreeMap<String, String>) -> &'static Theme {
+        if self.no_emoji {
+            if let Some(name) = configuration.get("theme").map(|s| s.trim()).filter(|name| *name != "text") {
+                self.log(LogLevel::Warn, &format!("⚠️  no_emoji is set, using the text theme instead of '{}'", name));
+            }
+            return THEMES.iter().find(|theme| theme.name == "text").unwrap_or(&THEMES[0]);
+        }
+
+        let name = configuration.get("theme").map(|s| s.trim()).unwrap_or(THEMES[0].name);
+        match THEMES.iter().find(|theme| theme.name == name) {
+            Some(theme) => theme,
+            None => {
+                self.log(LogLevel::Warn, &format!("⚠️  Unknown theme '{}', using '{}'", name, THEMES[0].name));
+                &THEMES[0]
+            }
+        }
+    }
+
+    fn parse_key(&self, configuration: &BTreeMap<String, String>, name: &str, default: char) -> Option<char> {
+        let Some(key) = configuration.get(name).map(|s| s.trim()) else {
+            return Some(default);
+        };
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => None,
+            (Some(c), None) => Some(c),
+            _ => {
+                self.log(LogLevel::Warn, &format!("⚠️  {} must be a single character, got '{}'", name, key));
+                Some(default)
+            }
+        }
+    }
+
+    // A configured fallback emoji, or `default` when it's missing or unusable
+    fn fallback_emoji(&self, configuration: &BTreeMap<String, String>, key: &str, default: &str) -> String {
+        let Some(emoji) = configuration.get(key) else {
+            return default.to_string();
+        };
+        match invalid_emoji_reason(emoji) {
+            Some(reason) => {
+                self.log(LogLevel::Warn, &format!("⚠️  Ignoring {}: emoji {}", key, reason));
+                default.to_string()
+            }
+            None => emoji.clone(),
+        }
+    }
+
+    // Picker entries: tabs in position order with our decoration stripped, presets by name
+    fn picker_tabs(&self) -> Vec<(usize, String)> {
+        let mut tabs: Vec<(usize, String)> = self.all_tabs.iter()
+            .map(|tab| (tab.position, self.clean_name(&self.tab_key(tab.position, None), &tab.name)))
+            .collect();
+        tabs.sort();
+        tabs
+    }
+
+    fn picker_presets(&self) -> Vec<(&String, &PresetConfig)> {
+        let mut presets: Vec<_> = self.presets.iter().collect();
+        presets.sort_by(|a, b| a.0.cmp(b.0));
+        presets
+    }
+
+    fn picker_key_pressed(&mut self, key: &BareKey) {
+        let Some(mut picker) = self.picker else {
+            return;
+        };
+        let tabs = self.picker_tabs();
+        let action = picker.handle_key(key, tabs.len(), self.presets.len());
+        self.picker = Some(picker);
+
+        match action {
+            PickerAction::Stay => {}
+            PickerAction::Close => self.picker = None,
+            PickerAction::Apply { tab, preset } => {
+                self.picker = None;
+                let Some(&(position, _)) = tabs.get(tab) else {
+                    return;
+                };
+                let Some((name, preset)) = self.picker_presets().get(preset).map(|(n, p)| ((*n).clone(), (*p).clone())) else {
+                    return;
+                };
+                self.log(LogLevel::Info, &format!("🏷️  PICKER: '{}' on tab {}", name, position));
+                self.notify_tab(position, &preset, None, None);
+            }
+        }
+    }
+
+    // The picker's current list, scrolled so the selection stays on screen
+    fn render_picker(&self, picker: &Picker, rows: usize, cols: usize) {
+        let (title, lines, selected) = if picker.choosing_preset {
+            let lines = self.picker_presets().into_iter()
+                .map(|(name, preset)| format!("{} {}", preset.emoji, name))
+                .collect::<Vec<_>>();
+            ("Put which preset on it? ↑/↓ Enter, Esc to go back", lines, picker.preset)
         } else {
-            if self.debug {
-                eprintln!("[zellij-notify] ⚠️  Could not identify target tab");
+            let lines = self.picker_tabs().into_iter()
+                .map(|(position, name)| format!("{} {}", position, name))
+                .collect::<Vec<_>>();
+            ("Tag which tab? ↑/↓ Enter, Esc to close", lines, picker.tab)
+        };
+
+        println!("{}", truncate(title, cols));
+        let visible = rows.saturating_sub(1);
+        let offset = (selected + 1).saturating_sub(visible);
+        for (index, line) in lines.iter().enumerate().skip(offset).take(visible) {
+            let marker = if index == selected { ">" } else { " " };
+            println!("{}", truncate(&format!("{} {}", marker, line), cols));
+        }
+    }
+
+    // Strip our decoration from every tab, as with the clear key
+    fn clear_all(&mut self) {
+        let mut keys: Vec<TabKey> = self.decorated_tabs.iter().cloned().collect();
+        keys.sort();
+        let positions: Vec<usize> = keys.iter().map(|key| key.position).collect();
+        self.log(LogLevel::Info, &format!("🧽 CLEAR ALL: tabs {:?}", positions));
+        for key in keys {
+            self.clean_tab(&key);
+        }
+    }
+
+    // Strip our decoration from a tab and forget its notification state
+    fn clean_tab(&mut self, key: &TabKey) {
+        let position = key.position;
+        // Keep what we know about the tab, so a later grant can still clean it
+        if !self.can_rename {
+            self.log(LogLevel::Warn, &format!("⚠️  Not cleaning tab {}: permission to rename tabs was denied", position));
+            return;
+        }
+        // Only a real clear starts the cooldown, not every focus of an undecorated tab
+        if self.cooldown_ms > 0 && self.decorated_tabs.contains(key) {
+            self.cleared_at.insert(key.clone(), now_secs());
+        }
+        if let Some(tab) = self.all_tabs.iter().find(|t| t.position == position) {
+            if let Some(cleaned) = self.undecorated_name(key, tab) {
+                self.log(LogLevel::Info, &format!("🔄 CLEAN: '{}' → '{}'", tab.name, cleaned));
+
+                rename_tab_at(position, cleaned);
             }
         }
+        self.forget_notification(key);
+    }
 
-        false // No UI re-render needed
+    // Strip a single managed emoji from a tab, leaving the rest of its decoration.
+    // Once nothing of ours is left this is a full clean.
+    fn clean_emoji(&mut self, key: &TabKey, emoji: &str) {
+        let position = key.position;
+        if !self.can_rename {
+            self.log(LogLevel::Warn, &format!("⚠️  Not cleaning tab {}: permission to rename tabs was denied", position));
+            return;
+        }
+        let Some(tab) = self.all_tabs.iter().find(|t| t.position == position) else {
+            return;
+        };
+        if !self.decorated_tabs.contains(key) {
+            return;
+        }
+
+        let stripped = strip_emoji(&tab.name, emoji, &self.emojis_of(key), &self.labels_of(key),
+            &self.separator, self.position);
+        if stripped == tab.name {
+            self.log(LogLevel::Debug, &format!("⏭️  Tab {} doesn't show {}, keeping it", position, emoji));
+            return;
+        }
+        if stripped == self.clean_name(key, &tab.name) {
+            self.clean_tab(key);
+            return;
+        }
+
+        self.log(LogLevel::Info, &format!("🔄 CLEAN {}: '{}' → '{}'", emoji, tab.name, stripped));
+        self.pending_renames.remove(key);
+        rename_tab_at(position, stripped);
+    }
+
+    // Whether the tab was cleared within the last cooldown_ms
+    fn in_cooldown(&self, key: &TabKey) -> bool {
+        if self.cooldown_ms == 0 {
+            return false;
+        }
+        self.cleared_at.get(key)
+            .is_some_and(|cleared| now_secs() - cleared < self.cooldown_ms as f64 / 1000.0)
+    }
+
+    // A decorated tab's name with our decoration removed; None for tabs we haven't
+    // decorated or with nothing left to strip
+    fn undecorated_name(&self, key: &TabKey, tab: &TabInfo) -> Option<String> {
+        if !self.decorated_tabs.contains(key) {
+            return None;
+        }
+        let cleaned = self.clean_name(key, &tab.name);
+
+        // Give back the name we shortened, unless the tab was renamed since
+        if let Some((full, short)) = self.full_names.get(key) {
+            if cleaned == *short {
+                return Some(full.clone());
+            }
+        }
+        (cleaned != tab.name).then_some(cleaned)
+    }
+
+    // Tab name with every plugin-managed decoration removed, including the raw emojis and
+    // messages put on the tab at `key`
+    fn clean_name(&self, key: &TabKey, name: &str) -> String {
+        strip_emojis(name, &self.emojis_of(key), &self.labels_of(key), &self.separator, self.position)
+    }
+
+    // The emojis stripped from the tab at `key`: the managed ones, and its own raw emojis
+    fn emojis_of(&self, key: &TabKey) -> Vec<String> {
+        let mut emojis = self.managed_emojis.clone();
+        if let Some(raw) = self.raw_emojis.get(key) {
+            emojis.extend(raw.iter().cloned());
+            sort_longest_first(&mut emojis);
+        }
+        emojis
+    }
+
+    // The labels stripped from the tab at `key`: every preset's, and its own messages
+    fn labels_of(&self, key: &TabKey) -> Vec<String> {
+        let mut labels = self.managed_labels.clone();
+        if let Some(messages) = self.message_labels.get(key) {
+            labels.extend(messages.iter().cloned());
+            sort_labels(&mut labels);
+        }
+        labels
+    }
+
+    // Drop per-tab notification bookkeeping once its emoji is gone
+    fn forget_notification(&mut self, key: &TabKey) {
+        self.notification_counts.remove(key);
+        self.decorated_tabs.remove(key);
+        self.full_names.remove(key);
+        self.notified_at.remove(key);
+        self.clear_after.remove(key);
+        self.notification_priorities.remove(key);
+        self.message_labels.remove(key);
+        self.raw_emojis.remove(key);
+        self.pending_renames.remove(key);
+    }
+
+    // Move every piece of per-tab state to the key `f` gives it, dropping what it maps to None.
+    // cleared_at is moved too, so a shifted tab keeps its cooldown.
+    fn rekey(&mut self, f: impl Fn(&TabKey) -> Option<TabKey>) {
+        fn moved<V>(map: &mut HashMap<TabKey, V>, f: &impl Fn(&TabKey) -> Option<TabKey>) {
+            *map = map.drain().filter_map(|(key, value)| Some((f(&key)?, value))).collect();
+        }
+        moved(&mut self.full_names, &f);
+        moved(&mut self.notification_counts, &f);
+        moved(&mut self.notified_at, &f);
+        moved(&mut self.clear_after, &f);
+        moved(&mut self.notification_priorities, &f);
+        moved(&mut self.message_labels, &f);
+        moved(&mut self.raw_emojis, &f);
+        moved(&mut self.cleared_at, &f);
+        moved(&mut self.pending_renames, &f);
+        self.decorated_tabs = self.decorated_tabs.drain().filter_map(|key| f(&key)).collect();
+    }
+
+    // Carry this session's per-tab state along with tabs that moved, and drop it for tabs
+    // that closed, so it never lands on whichever tab takes the old position
+    fn follow_tab_moves(&mut self, moves: &HashMap<usize, Option<usize>>) {
+        if moves.iter().all(|(old, new)| *new == Some(*old)) {
+            return;
+        }
+        self.log(LogLevel::Debug, &format!("🔀 Tabs moved: {:?}", moves));
+        let session = self.current_session().to_string();
+        self.rekey(|key| {
+            if key.session != session {
+                return Some(key.clone());
+            }
+            match moves.get(&key.position) {
+                Some(Some(position)) => Some(TabKey { position: *position, ..key.clone() }),
+                Some(None) => None,
+                None => Some(key.clone()),
+            }
+        });
     }
-}
 
-fn remove_trailing_emojis(name: &str) -> String {
-    let emojis = ["🔴", "✅", "❌", "⚠️", "⚡", "💼", "🎉", "❓"];
-    let mut cleaned = name.to_string();
+    // The key per-tab state for `position` is kept under: the session a message was routed
+    // by, else the session this instance runs in
+    fn tab_key(&self, position: usize, session_name: Option<&str>) -> TabKey {
+        let session = session_name.map(str::trim).filter(|s| !s.is_empty())
+            .unwrap_or_else(|| self.current_session());
+        TabKey { session: session.to_string(), position }
+    }
+
+    // Name of the session this instance runs in; empty until the first SessionUpdate
+    fn current_session(&self) -> &str {
+        self.sessions.iter().find(|s| s.is_current_session).map(|s| s.name.as_str()).unwrap_or("")
+    }
 
-    // Keep removing trailing emojis and whitespace
-    loop {
-        let original_len = cleaned.len();
-        cleaned = cleaned.trim_end().to_string();
+    // Rename a tab now, or hold the name until the debounce window closes.
+    // Later notifications inside the window replace the held name, so a burst
+    // ends in a single rename.
+    fn rename_or_defer(&mut self, key: &TabKey, new_name: String) {
+        if self.debounce_ms == 0 {
+            rename_tab_at(key.position, new_name);
+            return;
+        }
 
-        // Try to remove any trailing emoji (check all emojis, don't break early)
-        let mut found_emoji = false;
-        for emoji in emojis {
-            if cleaned.ends_with(emoji) {
-                cleaned = cleaned[..cleaned.len() - emoji.len()].to_string();
-                found_emoji = true;
-                break; // Found one, now trim again and recheck from the start
+        let window = self.debounce_ms as f64 / 1000.0;
+        match self.pending_renames.get_mut(key) {
+            Some((name, _)) => *name = new_name,
+            None => {
+                self.pending_renames.insert(key.clone(), (new_name, now_secs() + window));
+                set_timeout(window);
             }
         }
+    }
+
+    // Apply debounced renames whose window has closed
+    fn flush_pending_renames(&mut self) {
+        let now = now_secs();
+        // Timers can fire a hair early, so allow a small tolerance
+        let mut due: Vec<TabKey> = self.pending_renames.iter()
+            .filter(|(_, (_, flush_at))| now >= flush_at - 0.01)
+            .map(|(key, _)| key.clone())
+            .collect();
+        due.sort();
+
+        for key in due {
+            if let Some((name, _)) = self.pending_renames.remove(&key) {
+                self.log(LogLevel::Info, &format!("⏱️  Flushing debounced rename of tab {}: '{}'", key.position, name));
+                rename_tab_at(key.position, name);
+            }
+        }
+    }
+
+    // Strip emojis from tabs whose last notification is older than its timeout:
+    // the decorating preset's clear_after_secs, else the global one
+    fn clear_expired_notifications(&mut self) {
+        let now = now_secs();
+        let mut expired: Vec<(TabKey, u64)> = self.notified_at.iter()
+            .filter_map(|(key, notified)| {
+                let lifetime = *self.clear_after.get(key)?;
+                // Timers can fire a hair early, so allow a small tolerance
+                (now - notified >= lifetime as f64 - 0.1).then_some((key.clone(), lifetime))
+            })
+            .collect();
+        expired.sort();
+
+        for (key, lifetime) in expired {
+            self.log(LogLevel::Info, &format!("⏰ AUTO-CLEAR: tab {} after {}s", key.position, lifetime));
+            self.clean_tab(&key);
+        }
+    }
+}
 
-        // If nothing changed (no whitespace trimmed, no emoji removed), we're done
-        if !found_emoji && cleaned.len() == original_len {
+// Rough terminal width of a grapheme: emoji take two columns, everything else one
+fn grapheme_width(grapheme: &str) -> usize {
+    let wide = grapheme.chars().any(|c| {
+        c as u32 >= 0x1F000 || c == '\u{FE0F}' || ('\u{2600}'..='\u{27BF}').contains(&c)
+    });
+    if wide { 2 } else { 1 }
+}
+
+// Cut text to fit in `max` columns without splitting a grapheme, marking the cut with an ellipsis
+fn truncate(text: &str, max: usize) -> String {
+    let width: usize = text.graphemes(true).map(grapheme_width).sum();
+    if width <= max {
+        return text.to_string();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let w = grapheme_width(grapheme);
+        // Keep one column for the ellipsis
+        if used + w + 1 > max {
             break;
         }
+        out.push_str(grapheme);
+        used += w;
+    }
+    if max > 0 {
+        out.push('…');
+    }
+    out
+}
+
+// "1, 3,x,3" -> ([1, 3], ["x"]): positions in order without repeats, and the entries that
+// aren't a position. Entries count from `base` (a 0 is invalid with base 1); empty entries
+// (a trailing comma) are skipped silently
+fn parse_positions(list: &str, base: usize) -> (Vec<usize>, Vec<&str>) {
+    let mut positions = Vec::new();
+    let mut invalid = Vec::new();
+    for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        match entry.parse::<usize>().ok().and_then(|n| n.checked_sub(base)).ok_or(()) {
+            Ok(position) if !positions.contains(&position) => positions.push(position),
+            Ok(_) => {}
+            Err(_) => invalid.push(entry),
+        }
+    }
+    (positions, invalid)
+}
+
+// Rename the tab at a 0-based `TabInfo.position`; host::rename_tab_at turns it into Zellij's
+// 1-based index. Every rename goes through here so the conversion can't drift to a Vec index.
+fn rename_tab_at(position: usize, name: String) {
+    host::rename_tab_at(position, non_empty_name(position, name));
+}
+
+// A tab that only ever held an emoji strips down to nothing; give it Zellij's own
+// default name back rather than leaving it blank
+fn non_empty_name(position: usize, name: String) -> String {
+    if name.trim().is_empty() {
+        format!("Tab #{}", position + 1)
+    } else {
+        name
+    }
+}
+
+// Terminal pane id -> tab position. Ids are compared as strings, the way ZELLIJ_PANE_ID
+// arrives. Plugin panes (tab bar, status bar, this plugin) are numbered in their own id
+// space, so they're left out: their ids can equal a terminal pane's in another tab.
+fn pane_index(manifest: &PaneManifest) -> HashMap<String, usize> {
+    // PaneManifest.panes is keyed by tab position (0-indexed); floating panes are
+    // listed under their tab next to the tiled ones, so they're indexed too
+    manifest.panes.iter()
+        .flat_map(|(tab_position, panes)| {
+            panes.iter()
+                .filter(|pane| !pane.is_plugin)
+                .map(|pane| (pane.id.to_string(), *tab_position))
+        })
+        .collect()
+}
+
+// Where each tab of `old` went in `new`: its position in `new`, or None once it closed.
+// TabInfo has no stable id, so a tab is followed by its first pane that still exists.
+fn tab_moves(old: &PaneManifest, new: &PaneManifest) -> HashMap<usize, Option<usize>> {
+    let mut now_in = HashMap::new();
+    for (tab_position, panes) in &new.panes {
+        for pane in panes {
+            now_in.insert((pane.is_plugin, pane.id), *tab_position);
+        }
     }
+    old.panes.iter()
+        .map(|(tab_position, panes)| {
+            let moved_to = panes.iter().find_map(|pane| now_in.get(&(pane.is_plugin, pane.id)).copied());
+            (*tab_position, moved_to)
+        })
+        .collect()
+}
 
-    cleaned
+// Exit status 0 is a success; a nonzero status, or none at all (killed), is a failure
+fn command_status_emoji<'a>(exit_status: Option<i32>, success: &'a str, failure: &'a str) -> &'a str {
+    match exit_status {
+        Some(0) => success,
+        _ => failure,
+    }
+}
+
+// [{"position": 0, "name": "build", "emoji": "✅×2"}, ...]
+fn query_reply(pending: &[PendingNotification]) -> String {
+    serde_json::to_string(pending).unwrap_or_else(|_| "[]".to_string())
+}
+
+// A mute_tabs entry is a tab position or a tab name, where `*` matches any run of characters
+fn is_muted(patterns: &[String], position: usize, name: &str) -> bool {
+    patterns.iter().any(|pattern| match pattern.parse::<usize>() {
+        Ok(muted) => muted == position,
+        Err(_) => glob_match(pattern, name),
+    })
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut remaining) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    // Each literal piece must appear in order; the last one has to end the text
+    let pieces: Vec<&str> = rest.split('*').collect();
+    for (i, piece) in pieces.iter().enumerate() {
+        if i == pieces.len() - 1 {
+            return remaining.ends_with(piece);
+        }
+        match remaining.find(piece) {
+            Some(at) => remaining = &remaining[at + piece.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+// log_level wins when set; otherwise debug "true" keeps its old meaning of full logging
+fn log_level(configuration: &BTreeMap<String, String>) -> LogLevel {
+    if let Some(level) = configuration.get("log_level").and_then(|s| s.parse().ok()) {
+        return level;
+    }
+    match configuration.get("debug").and_then(|s| s.parse().ok()) {
+        Some(true) => LogLevel::Debug,
+        _ => LogLevel::Warn,
+    }
+}
+
+fn now_secs() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+// Collect every emoji the plugin can append: the fallbacks plus all configured presets
+fn managed_emojis(presets: &HashMap<String, PresetConfig>, fallbacks: &[&str]) -> Vec<String> {
+    let mut emojis: Vec<String> = presets.values()
+        .flat_map(|p| [Some(&p.emoji), p.foreground_emoji.as_ref(), p.background_emoji.as_ref()])
+        .flatten()
+        .filter(|e| !e.is_empty())
+        .cloned()
+        .collect();
+    emojis.extend(fallbacks.iter().map(|e| e.to_string()));
+    sort_longest_first(&mut emojis);
+    emojis
+}
+
+// Longest first (in grapheme clusters) so a "⚠️🔴" preset is stripped whole
+// before a lone "🔴" preset can match its tail
+fn sort_longest_first(emojis: &mut Vec<String>) {
+    emojis.sort_by(|a, b| {
+        b.graphemes(true).count().cmp(&a.graphemes(true).count()).then_with(|| a.cmp(b))
+    });
+    emojis.dedup();
+}
+
+fn managed_labels(presets: &HashMap<String, PresetConfig>) -> Vec<String> {
+    let mut labels: Vec<String> = presets.values()
+        .filter_map(|p| p.label.clone())
+        .filter(|l| !l.trim().is_empty())
+        .collect();
+    sort_labels(&mut labels);
+    labels
+}
+
+// Longest first so "done!" is tried before "done"
+fn sort_labels(labels: &mut Vec<String>) {
+    labels.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    labels.dedup();
+}
+
+// A `message` arg as it goes on the tab: one line, at most MAX_MESSAGE_LEN wide
+fn clean_message(message: &str) -> String {
+    let line = message.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate(&line, MAX_MESSAGE_LEN)
+}
+
+// "🔴" + "✅" -> "🔴✅"; an emoji that already is the marker stays as it is
+fn with_marker(emoji: &str, marker: &str) -> String {
+    if emoji.starts_with(marker) {
+        emoji.to_string()
+    } else {
+        format!("{}{}", marker, emoji)
+    }
+}
+
+// The tab name a notification produces: `base` with the emoji, its repeat count and any
+// label attached on the configured side
+fn render_name(base: &str, emoji: &str, count: u32, label: Option<&str>, style: &NameStyle) -> String {
+    let indicator = with_label(&badge(emoji, count, style.max_count), label);
+    apply(base, &indicator, style.position, style.separator)
+}
+
+// "✅" + Some("done") -> "✅ done"
+fn with_label(indicator: &str, label: Option<&str>) -> String {
+    match label {
+        Some(label) if !label.trim().is_empty() => format!("{} {}", indicator, label.trim()),
+        _ => indicator.to_string(),
+    }
+}
+
+// Render the indicator, adding a ×N count once a tab has been notified more than once,
+// or ×N+ once the count went past a non-zero `max_count`
+fn badge(emoji: &str, count: u32, max_count: u32) -> String {
+    if max_count > 0 && count > max_count {
+        format!("{}×{}+", emoji, max_count)
+    } else if count > 1 {
+        format!("{}×{}", emoji, count)
+    } else {
+        emoji.to_string()
+    }
 }
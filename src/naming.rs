@@ -0,0 +1,426 @@
+// Adding and stripping the emoji decoration on a tab name, independent of plugin state.
+// Everything matches on whole grapheme clusters, so a managed "💻" never eats into a
+// user's "👨‍💻", and "⚠" and "⚠️" count as the same emoji.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The separator the plugin uses when none is configured.
+pub const DEFAULT_SEPARATOR: &str = " ";
+
+/// Which side of the tab name the emoji goes on.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum EmojiPosition {
+    Prefix,
+    #[default]
+    Suffix,
+}
+
+/// Attach `emoji` on the `position` side of `name`, with `separator` in between.
+///
+/// ```
+/// use zellij_notify::naming::{apply, EmojiPosition};
+///
+/// assert_eq!(apply("build", "✅", EmojiPosition::Suffix, " "), "build ✅");
+/// assert_eq!(apply("build", "✅", EmojiPosition::Prefix, " | "), "✅ | build");
+/// ```
+pub fn apply(name: &str, emoji: &str, position: EmojiPosition, separator: &str) -> String {
+    match position {
+        EmojiPosition::Suffix => format!("{}{}{}", name, separator, emoji),
+        EmojiPosition::Prefix => format!("{}{}{}", emoji, separator, name),
+    }
+}
+
+/// Strip any of `emojis` (and a `×N` count after them) from the end of `name`, the way the
+/// plugin does with its default suffix position and separator.
+///
+/// ```
+/// use zellij_notify::naming::strip_managed;
+///
+/// let emojis = vec!["✅".to_string(), "⚠️".to_string()];
+/// assert_eq!(strip_managed("build ✅×3", &emojis), "build");
+/// assert_eq!(strip_managed("deploy ⚠ ✅", &emojis), "deploy");
+/// // Unmanaged emojis are part of the name
+/// assert_eq!(strip_managed("👨‍💻 dev", &emojis), "👨‍💻 dev");
+/// ```
+pub fn strip_managed(name: &str, emojis: &[String]) -> String {
+    strip_emojis(name, emojis, &[], DEFAULT_SEPARATOR, EmojiPosition::default())
+}
+
+/// Strip managed `emojis` from the `position` side of `name`, along with their `×N` counts,
+/// any of `labels` that follow them as a separate word, and `separator`.
+///
+/// ```
+/// use zellij_notify::naming::{strip_emojis, EmojiPosition};
+///
+/// let emojis = vec!["✅".to_string()];
+/// let labels = vec!["done".to_string()];
+/// assert_eq!(strip_emojis("✅ done | build", &emojis, &labels, " | ", EmojiPosition::Prefix), "build");
+/// ```
+pub fn strip_emojis(
+    name: &str,
+    emojis: &[String],
+    labels: &[String],
+    separator: &str,
+    position: EmojiPosition,
+) -> String {
+    match position {
+        EmojiPosition::Suffix => remove_trailing_emojis(name, emojis, labels, separator),
+        EmojiPosition::Prefix => remove_leading_emojis(name, emojis, labels, separator),
+    }
+}
+
+/// Strip only `emoji` (with its `×N` count and label) from the decoration on the `position`
+/// side of `name`, keeping any other managed emojis there. `emoji` has to be one of `emojis`.
+///
+/// ```
+/// use zellij_notify::naming::{strip_emoji, EmojiPosition};
+///
+/// let emojis = vec!["❌".to_string(), "ℹ️".to_string()];
+/// assert_eq!(strip_emoji("api ℹ️ ❌×2", "❌", &emojis, &[], " ", EmojiPosition::Suffix), "api ℹ️");
+/// // Nothing to strip when the tab doesn't show that emoji
+/// assert_eq!(strip_emoji("api ℹ️", "❌", &emojis, &[], " ", EmojiPosition::Suffix), "api ℹ️");
+/// ```
+pub fn strip_emoji(
+    name: &str,
+    emoji: &str,
+    emojis: &[String],
+    labels: &[String],
+    separator: &str,
+    position: EmojiPosition,
+) -> String {
+    let (base, decorations) = match position {
+        EmojiPosition::Suffix => split_trailing(name, emojis, labels, separator),
+        EmojiPosition::Prefix => split_leading(name, emojis, labels, separator),
+    };
+
+    let wanted = normalize_emoji(emoji);
+    if !decorations.iter().any(|d| normalize_emoji(&d.emoji) == wanted) {
+        return name.to_string();
+    }
+
+    let kept: Vec<&str> = decorations.iter()
+        .filter(|d| normalize_emoji(&d.emoji) != wanted)
+        .map(|d| d.text.as_str())
+        .collect();
+    if kept.is_empty() {
+        return base;
+    }
+    apply(&base, &kept.join(" "), position, separator)
+}
+
+/// Whether `name` can only have come from the plugin: a non-empty base with a managed
+/// decoration that either carries a `×N` count or is attached with a non-default
+/// `separator`, exactly the way the plugin renders it. A plain "release ✅" may well be a
+/// name the user typed, so it doesn't count.
+///
+/// ```
+/// use zellij_notify::naming::{is_rendered, EmojiPosition};
+///
+/// let emojis = vec!["✅".to_string()];
+/// assert!(is_rendered("build ✅×2", &emojis, &[], " ", EmojiPosition::Suffix));
+/// assert!(is_rendered("build | ✅", &emojis, &[], " | ", EmojiPosition::Suffix));
+/// assert!(!is_rendered("release ✅", &emojis, &[], " ", EmojiPosition::Suffix));
+/// assert!(!is_rendered("✅×2", &emojis, &[], " ", EmojiPosition::Suffix));
+/// ```
+pub fn is_rendered(
+    name: &str,
+    emojis: &[String],
+    labels: &[String],
+    separator: &str,
+    position: EmojiPosition,
+) -> bool {
+    let (base, decorations) = match position {
+        EmojiPosition::Suffix => split_trailing(name, emojis, labels, separator),
+        EmojiPosition::Prefix => split_leading(name, emojis, labels, separator),
+    };
+    if base.trim().is_empty() || decorations.is_empty() {
+        return false;
+    }
+
+    let counted = decorations.iter().any(|d| d.text.contains('×'));
+    let texts: Vec<&str> = decorations.iter().map(|d| d.text.as_str()).collect();
+    let separated = separator != DEFAULT_SEPARATOR && apply(&base, &texts.join(" "), position, separator) == name;
+    counted || separated
+}
+
+// One managed emoji found in a tab's decoration
+struct Decoration {
+    emoji: String,  // The managed emoji that matched
+    text: String,  // As shown on the tab, with its count badge and label
+}
+
+fn remove_trailing_emojis(name: &str, emojis: &[String], labels: &[String], separator: &str) -> String {
+    split_trailing(name, emojis, labels, separator).0
+}
+
+fn remove_leading_emojis(name: &str, emojis: &[String], labels: &[String], separator: &str) -> String {
+    split_leading(name, emojis, labels, separator).0
+}
+
+// The name with trailing managed emojis removed, and those emojis in tab-name order.
+// `cleaned` only ever shrinks from the end, so it stays a prefix of `name`
+fn split_trailing(name: &str, emojis: &[String], labels: &[String], separator: &str) -> (String, Vec<Decoration>) {
+    let mut cleaned = name.to_string();
+    let mut decorations = Vec::new();
+
+    // Keep removing trailing emojis and whitespace
+    loop {
+        let original_len = cleaned.len();
+        cleaned = trim_end_blank(&cleaned);
+        let decoration_end = cleaned.len();
+
+        // A trailing label or ×N count badge only goes away together with its emoji,
+        // so try with the label removed first and then without
+        let mut candidates = Vec::new();
+        if let Some(rest) = strip_trailing_label(&cleaned, labels) {
+            candidates.push(rest);
+        }
+        candidates.push(cleaned.clone());
+
+        // Try to remove any trailing emoji (check all emojis, don't break early)
+        let mut found_emoji = false;
+        'candidates: for candidate in candidates {
+            let candidate = strip_trailing_count(&candidate).unwrap_or(candidate);
+            for emoji in emojis {
+                if let Some(len) = trailing_emoji_len(&candidate, emoji) {
+                    let decoration_start = candidate.len() - len;
+                    decorations.push(Decoration {
+                        emoji: emoji.clone(),
+                        text: name[decoration_start..decoration_end].to_string(),
+                    });
+                    cleaned = candidate[..decoration_start].to_string();
+                    cleaned = strip_trailing_separator(&cleaned, separator);
+                    found_emoji = true;
+                    break 'candidates; // Found one, now trim again and recheck from the start
+                }
+            }
+        }
+
+        // If nothing changed (no whitespace trimmed, no emoji removed), we're done
+        if !found_emoji && cleaned.len() == original_len {
+            break;
+        }
+    }
+
+    decorations.reverse();
+    (cleaned, decorations)
+}
+
+// Mirror of split_trailing, working from the start of the name
+fn split_leading(name: &str, emojis: &[String], labels: &[String], separator: &str) -> (String, Vec<Decoration>) {
+    let mut cleaned = name.to_string();
+    let mut decorations = Vec::new();
+
+    loop {
+        let original_len = cleaned.len();
+        cleaned = trim_start_blank(&cleaned);
+
+        let mut found_emoji = false;
+        for emoji in emojis {
+            if let Some(len) = leading_emoji_len(&cleaned, emoji) {
+                let decorated = cleaned.clone();
+                cleaned = cleaned[len..].to_string();
+                // The ×N badge sits right after the emoji in prefix mode
+                if let Some(rest) = strip_leading_count(&cleaned) {
+                    cleaned = rest;
+                }
+                if let Some(rest) = strip_leading_label(&cleaned, labels) {
+                    cleaned = rest;
+                }
+                decorations.push(Decoration {
+                    emoji: emoji.clone(),
+                    text: decorated[..decorated.len() - cleaned.len()].trim_end().to_string(),
+                });
+                cleaned = strip_leading_separator(&cleaned, separator);
+                found_emoji = true;
+                break;
+            }
+        }
+
+        if !found_emoji && cleaned.len() == original_len {
+            break;
+        }
+    }
+
+    (cleaned, decorations)
+}
+
+fn is_variation_selector(c: char) -> bool {
+    matches!(c, '\u{FE0E}' | '\u{FE0F}')
+}
+
+// Drop variation selectors so "⚠" and "⚠️" compare equal
+fn normalize_emoji(s: &str) -> String {
+    s.chars().filter(|c| !is_variation_selector(*c)).collect()
+}
+
+// Whitespace, or a stray variation selector left attached to whitespace
+fn is_blank(grapheme: &str) -> bool {
+    grapheme.chars().all(|c| c.is_whitespace() || is_variation_selector(c))
+}
+
+fn trim_end_blank(name: &str) -> String {
+    let mut end = name.len();
+    for (idx, grapheme) in name.grapheme_indices(true).rev() {
+        if !is_blank(grapheme) {
+            break;
+        }
+        end = idx;
+    }
+    name[..end].to_string()
+}
+
+fn trim_start_blank(name: &str) -> String {
+    let mut start = 0;
+    for (idx, grapheme) in name.grapheme_indices(true) {
+        if !is_blank(grapheme) {
+            break;
+        }
+        start = idx + grapheme.len();
+    }
+    name[start..].to_string()
+}
+
+// Byte length of `emoji` at the end of `name`, matched on whole grapheme clusters
+// so a managed "💻" never eats half of a user's "👨‍💻"
+fn trailing_emoji_len(name: &str, emoji: &str) -> Option<usize> {
+    let wanted = normalize_emoji(emoji);
+    if wanted.is_empty() {
+        return None;
+    }
+
+    let count = emoji.graphemes(true).count();
+    let tail: Vec<&str> = name.graphemes(true).rev().take(count).collect();
+    if tail.len() < count {
+        return None;
+    }
+
+    let len: usize = tail.iter().map(|g| g.len()).sum();
+    if normalize_emoji(&name[name.len() - len..]) == wanted {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+// Byte length of `emoji` at the start of `name`, matched on whole grapheme clusters
+fn leading_emoji_len(name: &str, emoji: &str) -> Option<usize> {
+    let wanted = normalize_emoji(emoji);
+    if wanted.is_empty() {
+        return None;
+    }
+
+    let count = emoji.graphemes(true).count();
+    let head: Vec<&str> = name.graphemes(true).take(count).collect();
+    if head.len() < count {
+        return None;
+    }
+
+    let len: usize = head.iter().map(|g| g.len()).sum();
+    if normalize_emoji(&name[..len]) == wanted {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+// "name | " -> "name" for separator " | "; whitespace-only separators are handled by trimming
+fn strip_trailing_separator(name: &str, separator: &str) -> String {
+    let trimmed = name.trim_end();
+    match trimmed.strip_suffix(separator.trim()) {
+        Some(rest) if !separator.trim().is_empty() => rest.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+// " | name" -> " name" for separator " | "
+fn strip_leading_separator(name: &str, separator: &str) -> String {
+    let trimmed = name.trim_start();
+    match trimmed.strip_prefix(separator.trim()) {
+        Some(rest) if !separator.trim().is_empty() => rest.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+// "name ✅ done" -> "name ✅", only when the label is a separate word
+fn strip_trailing_label(name: &str, labels: &[String]) -> Option<String> {
+    for label in labels {
+        if let Some(rest) = name.strip_suffix(label.trim()) {
+            if rest.ends_with(char::is_whitespace) {
+                return Some(rest.trim_end().to_string());
+            }
+        }
+    }
+    None
+}
+
+// " done name" -> " name", only when the label is a separate word
+fn strip_leading_label(name: &str, labels: &[String]) -> Option<String> {
+    let trimmed = name.trim_start();
+    if trimmed.len() == name.len() {
+        return None;
+    }
+    for label in labels {
+        if let Some(rest) = trimmed.strip_prefix(label.trim()) {
+            if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                return Some(rest.to_string());
+            }
+        }
+    }
+    None
+}
+
+// "name ✅×3" or "name ✅×3+" -> "name ✅"
+fn strip_trailing_count(name: &str) -> Option<String> {
+    let name = name.strip_suffix('+').unwrap_or(name);
+    let without_digits = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    if without_digits.len() == name.len() {
+        return None;
+    }
+    without_digits.strip_suffix('×').map(|s| s.to_string())
+}
+
+// "×3 name" or "×3+ name" -> " name"
+fn strip_leading_count(name: &str) -> Option<String> {
+    let rest = name.strip_prefix('×')?;
+    let without_digits = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    if without_digits.len() == rest.len() {
+        return None;
+    }
+    Some(without_digits.strip_prefix('+').unwrap_or(without_digits).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emojis(list: &[&str]) -> Vec<String> {
+        list.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn warning_sign_matches_with_and_without_variation_selector() {
+        let managed = emojis(&["⚠️"]);
+        assert_eq!(strip_managed("deploy ⚠️", &managed), "deploy");
+        assert_eq!(strip_managed("deploy ⚠", &managed), "deploy");
+        assert_eq!(strip_managed("deploy ⚠", &emojis(&["⚠"])), "deploy");
+        assert_eq!(strip_managed("deploy ⚠️", &emojis(&["⚠"])), "deploy");
+    }
+
+    #[test]
+    fn zwj_sequences_are_whole_graphemes() {
+        // A managed part of a ZWJ sequence never eats into it
+        assert_eq!(strip_managed("dev 👨‍💻", &emojis(&["💻"])), "dev 👨‍💻");
+        // A managed ZWJ sequence strips whole
+        assert_eq!(strip_managed("dev 👨‍💻", &emojis(&["👨‍💻", "💻"])), "dev");
+        assert_eq!(strip_managed("ops 🏳️‍🌈 ✅", &emojis(&["✅"])), "ops 🏳️‍🌈");
+    }
+
+    #[test]
+    fn user_typed_emoji_outside_the_set_is_kept() {
+        let managed = emojis(&["✅", "⚡"]);
+        assert_eq!(strip_managed("🔥 hot", &managed), "🔥 hot");
+        assert_eq!(strip_managed("hot 🔥", &managed), "hot 🔥");
+        assert_eq!(strip_managed("hot 🔥 ✅", &managed), "hot 🔥");
+    }
+}
@@ -0,0 +1,363 @@
+// Working out which tab a pipe message is about, from the tabs and panes the plugin has seen.
+// Kept apart from State so the precedence can be exercised without a running Zellij.
+
+use std::collections::{BTreeMap, HashMap};
+use regex::Regex;
+use zellij_tile::prelude::{PaneManifest, TabInfo};
+
+use crate::LogLevel;
+
+// What resolution looks at; `clean_name` strips the decoration of the tab at a position
+// from a name and `log` is State::log
+pub(crate) struct TargetView<'a> {
+    pub all_tabs: &'a [TabInfo],
+    pub pane_manifest: Option<&'a PaneManifest>,
+    pub pane_tabs: &'a HashMap<String, usize>,  // Pane id -> tab position, as kept by State
+    pub tab_position_base: usize,  // What a tab_position arg calls the first tab: 0 or 1
+    pub clean_name: &'a dyn Fn(usize, &str) -> String,
+    pub log: &'a dyn Fn(LogLevel, &str),
+}
+
+// Identify which tab a pipe message is about.
+// Precedence: pane_id > pane_title_regex > tab_position > tab_number > tab_name > active tab. The active tab is
+// only used when no targeting arg was given at all, so a stale pane_id never
+// silently lands on whatever tab happens to be focused.
+pub(crate) fn resolve_target(args: &BTreeMap<String, String>, view: &TargetView) -> Option<usize> {
+    (view.log)(LogLevel::Debug, "🧭 Resolving target (precedence: pane_id > pane_title_regex > tab_position > tab_number > tab_name > active)");
+
+    let pane_id = args.get("pane_id");
+    let pane_title = args.get("pane_title_regex");
+    let tab_position = args.get("tab_position");
+    let tab_number = args.get("tab_number");
+    let tab_name = args.get("tab_name");
+
+    // Method 1: Check if pane_id was passed via args (from shell wrapper)
+    if let Some(pane_id) = pane_id {
+        if let Some(position) = view.resolve_by_pane_id(pane_id) {
+            return Some(position);
+        }
+    }
+
+    if let Some(pattern) = pane_title {
+        // Method 2: The tab holding a pane whose title matches
+        if let Some(position) = view.resolve_by_pane_title(pattern) {
+            return Some(position);
+        }
+    }
+
+    if let Some(pos_str) = tab_position {
+        // Method 3: Check if tab position was explicitly passed via args
+        (view.log)(LogLevel::Debug, &format!("🎯 Tab position explicitly provided: {}", pos_str));
+        match pos_str.parse::<usize>().ok().map(|n| n.checked_sub(view.tab_position_base)) {
+            Some(Some(position)) => {
+                (view.log)(LogLevel::Debug, &format!("🎯 tab_position {} counting from {} is tab index {}",
+                    pos_str, view.tab_position_base, position));
+                return Some(position);
+            }
+            Some(None) => (view.log)(LogLevel::Warn, &format!("⚠️  tab_position {} is before the first tab (tabs count from {})",
+                pos_str, view.tab_position_base)),
+            None => (view.log)(LogLevel::Warn, &format!("⚠️  tab_position must be a number, got '{}'", pos_str)),
+        }
+    }
+
+    if let Some(number) = tab_number {
+        // Method 3b: The tab the bar shows as number N, counting from 1
+        if let Some(position) = view.resolve_by_tab_number(number) {
+            return Some(position);
+        }
+    }
+
+    if let Some(name) = tab_name {
+        // Method 4: Match the tab by name, ignoring any emojis we added
+        if let Some(position) = view.resolve_by_tab_name(name) {
+            return Some(position);
+        }
+    }
+
+    if pane_id.is_some() || pane_title.is_some() || tab_position.is_some() || tab_number.is_some() || tab_name.is_some() {
+        return None;
+    }
+
+    // Method 5: Fall back to the currently active tab from our stored state
+    // This is NOT reliable for background commands but works for immediate commands
+    let active_tab = view.all_tabs.iter().find(|t| t.active);
+    if let Some(tab) = active_tab {
+        (view.log)(LogLevel::Debug, &format!("🎯 Using active tab from state: {} '{}'",
+            tab.position, tab.name));
+    } else {
+        (view.log)(LogLevel::Warn, "⚠️  No active tab found in state");
+    }
+    active_tab.map(|t| t.position)
+}
+
+impl TargetView<'_> {
+    fn resolve_by_pane_id(&self, pane_id: &str) -> Option<usize> {
+        (self.log)(LogLevel::Debug, &format!("🆔 Pane ID provided: {}", pane_id));
+
+        let Some(tab_position) = self.find_pane_tab(pane_id) else {
+            (self.log)(LogLevel::Warn, &format!("⚠️  Pane ID {} not found in PaneManifest", pane_id));
+            return None;
+        };
+
+        // Floating panes are listed under the tab they float over, so they resolve like tiled
+        // ones. If that tab isn't one we know (the manifest can run ahead of TabUpdate), the
+        // pane can't be attributed and we fall back to the active tab.
+        let known_tab = self.all_tabs.is_empty() || self.all_tabs.iter().any(|t| t.position == tab_position);
+        if !known_tab && self.is_floating_pane(pane_id) {
+            let active = self.all_tabs.iter().find(|t| t.active).map(|t| t.position);
+            (self.log)(LogLevel::Warn, &format!("⚠️  Floating pane {} is in unknown tab {}, using active tab {:?}",
+                pane_id, tab_position, active));
+            return active;
+        }
+
+        (self.log)(LogLevel::Debug, &format!("✅ Found pane {} in tab {}", pane_id, tab_position));
+        Some(tab_position)
+    }
+
+    fn find_pane_tab(&self, pane_id: &str) -> Option<usize> {
+        if let Some(tab_position) = self.pane_tabs.get(pane_id) {
+            return Some(*tab_position);
+        }
+
+        // Fall back to scanning the PaneManifest when the index hasn't been built
+        if self.pane_tabs.is_empty() {
+            let Some(manifest) = self.pane_manifest else {
+                (self.log)(LogLevel::Warn, "⚠️  No PaneManifest available yet");
+                return None;
+            };
+
+            // PaneManifest.panes is a HashMap<usize, Vec<PaneInfo>>
+            // where the key is the tab position (0-indexed)
+            for (tab_position, panes) in &manifest.panes {
+                // Check if any terminal pane in this tab matches our pane_id; plugin pane ids are
+            // a separate id space
+                if panes.iter().any(|pane| !pane.is_plugin && pane.id.to_string() == pane_id) {
+                    return Some(*tab_position);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn is_floating_pane(&self, pane_id: &str) -> bool {
+        self.pane_manifest.is_some_and(|manifest| {
+            manifest.panes.values()
+                .flatten()
+                .any(|pane| pane.is_floating && !pane.is_plugin && pane.id.to_string() == pane_id)
+        })
+    }
+
+    fn resolve_by_pane_title(&self, pattern: &str) -> Option<usize> {
+        (self.log)(LogLevel::Debug, &format!("🔤 Pane title pattern provided: {}", pattern));
+
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(e) => {
+                (self.log)(LogLevel::Warn, &format!("⚠️  Invalid pane_title_regex '{}': {}", pattern, e));
+                return None;
+            }
+        };
+        let Some(manifest) = self.pane_manifest else {
+            (self.log)(LogLevel::Warn, "⚠️  No PaneManifest available yet");
+            return None;
+        };
+
+        // Lowest tab wins when several panes match, as with duplicate pane ids
+        let mut positions: Vec<&usize> = manifest.panes.keys().collect();
+        positions.sort();
+        for position in positions {
+            let matched = manifest.panes[position].iter()
+                .find(|pane| !pane.is_plugin && regex.is_match(&pane.title));
+            if let Some(pane) = matched {
+                (self.log)(LogLevel::Debug, &format!("✅ Pane {} '{}' matches, in tab {}", pane.id, pane.title, position));
+                return Some(*position);
+            }
+        }
+
+        (self.log)(LogLevel::Warn, &format!("⚠️  No pane title matches '{}'", pattern));
+        None
+    }
+
+    // The Nth tab in position order, whatever positions Zellij gave the tabs
+    fn resolve_by_tab_number(&self, number: &str) -> Option<usize> {
+        (self.log)(LogLevel::Debug, &format!("🔢 Tab number provided: {}", number));
+
+        let Some(index) = number.trim().parse::<usize>().ok().and_then(|n| n.checked_sub(1)) else {
+            (self.log)(LogLevel::Warn, &format!("⚠️  tab_number must be 1 or more, got '{}'", number));
+            return None;
+        };
+        let mut positions: Vec<usize> = self.all_tabs.iter().map(|t| t.position).collect();
+        positions.sort();
+
+        match positions.get(index) {
+            Some(position) => {
+                (self.log)(LogLevel::Debug, &format!("✅ Tab number {} is position {}", number, position));
+                Some(*position)
+            }
+            None => {
+                (self.log)(LogLevel::Warn, &format!("⚠️  No tab number {} ({} tabs)", number, positions.len()));
+                None
+            }
+        }
+    }
+
+    fn resolve_by_tab_name(&self, name: &str) -> Option<usize> {
+        (self.log)(LogLevel::Debug, &format!("🏷️  Tab name provided: {}", name));
+
+        // A decorated name only matches the tab carrying that decoration
+        let found = self.all_tabs.iter()
+            .find(|t| (self.clean_name)(t.position, name) == (self.clean_name)(t.position, &t.name))
+            .map(|t| t.position);
+
+        match found {
+            Some(position) => (self.log)(LogLevel::Debug, &format!("✅ Found tab '{}' at position {}", name, position)),
+            None => (self.log)(LogLevel::Warn, &format!("⚠️  No tab named '{}'", name)),
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use zellij_tile::prelude::PaneInfo;
+
+    // Tabs 0 "editor" (active), 1 "build ✅" and 2 "logs"; pane 7 titled "cargo test" is in
+    // tab 1 and pane 9 titled "tail -f" in tab 2
+    fn manifest() -> PaneManifest {
+        let pane = |id: u32, title: &str| PaneInfo { id, title: title.to_string(), ..Default::default() };
+        PaneManifest {
+            panes: HashMap::from([(0, vec![pane(1, "nvim")]), (1, vec![pane(7, "cargo test")]), (2, vec![pane(9, "tail -f")])]),
+        }
+    }
+
+    fn tabs() -> Vec<TabInfo> {
+        ["editor", "build ✅", "logs"].iter().enumerate()
+            .map(|(position, name)| TabInfo { position, name: name.to_string(), active: position == 0, ..Default::default() })
+            .collect()
+    }
+
+    fn args(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    // Resolve `pairs` against the tabs above, returning the tab and the warnings logged
+    fn resolve(pairs: &[(&str, &str)]) -> (Option<usize>, Vec<String>) {
+        resolve_in(&manifest(), pairs)
+    }
+
+    fn resolve_in(manifest: &PaneManifest, pairs: &[(&str, &str)]) -> (Option<usize>, Vec<String>) {
+        resolve_indexed(manifest, &crate::pane_index(manifest), pairs)
+    }
+
+    fn resolve_indexed(manifest: &PaneManifest, pane_tabs: &HashMap<String, usize>, pairs: &[(&str, &str)]) -> (Option<usize>, Vec<String>) {
+        let tabs = tabs();
+        let warnings = RefCell::new(Vec::new());
+        let log = |level: LogLevel, message: &str| {
+            if level == LogLevel::Warn {
+                warnings.borrow_mut().push(message.to_string());
+            }
+        };
+        let view = TargetView {
+            all_tabs: &tabs,
+            pane_manifest: Some(manifest),
+            pane_tabs,
+            tab_position_base: 0,
+            clean_name: &|_, name: &str| name.trim_end_matches(" ✅").to_string(),
+            log: &log,
+        };
+        let target = resolve_target(&args(pairs), &view);
+        (target, warnings.into_inner())
+    }
+
+    #[test]
+    fn pane_id_wins_over_everything_else() {
+        let all = [("pane_id", "9"), ("pane_title_regex", "cargo"), ("tab_position", "0"), ("tab_number", "1"), ("tab_name", "build")];
+        assert_eq!(resolve(&all).0, Some(2));
+    }
+
+    #[test]
+    fn pane_title_regex_wins_over_tab_args() {
+        let args = [("pane_title_regex", "^cargo"), ("tab_position", "0"), ("tab_number", "1"), ("tab_name", "logs")];
+        assert_eq!(resolve(&args).0, Some(1));
+    }
+
+    #[test]
+    fn pane_title_regex_resolves_the_tab_of_a_matching_pane() {
+        assert_eq!(resolve(&[("pane_title_regex", "test$")]).0, Some(1));
+        assert_eq!(resolve(&[("pane_title_regex", "tail|nvim")]).0, Some(0));
+
+        let (target, warnings) = resolve(&[("pane_title_regex", "(cargo"), ("tab_name", "logs")]);
+        assert_eq!(target, Some(2));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("⚠️  Invalid pane_title_regex '(cargo'"));
+    }
+
+    #[test]
+    fn tab_position_wins_over_tab_number_and_name() {
+        assert_eq!(resolve(&[("tab_position", "2"), ("tab_number", "1"), ("tab_name", "build")]).0, Some(2));
+    }
+
+    #[test]
+    fn tab_number_wins_over_tab_name() {
+        assert_eq!(resolve(&[("tab_number", "3"), ("tab_name", "build")]).0, Some(2));
+    }
+
+    #[test]
+    fn tab_name_ignores_our_decoration() {
+        assert_eq!(resolve(&[("tab_name", "build")]).0, Some(1));
+    }
+
+    #[test]
+    fn active_tab_only_without_targeting_args() {
+        assert_eq!(resolve(&[]).0, Some(0));
+        assert_eq!(resolve(&[("pane_id", "42")]).0, None);
+    }
+
+    #[test]
+    fn unresolved_args_fall_through_to_the_next() {
+        assert_eq!(resolve(&[("pane_id", "42"), ("tab_name", "logs")]).0, Some(2));
+        assert_eq!(resolve(&[("pane_title_regex", "htop"), ("tab_number", "2")]).0, Some(1));
+    }
+
+    #[test]
+    fn non_numeric_tab_position_warns_and_falls_through() {
+        let (target, warnings) = resolve(&[("tab_position", "two"), ("tab_name", "logs")]);
+        assert_eq!(target, Some(2));
+        assert_eq!(warnings, vec!["⚠️  tab_position must be a number, got 'two'".to_string()]);
+
+        assert_eq!(resolve(&[("tab_position", "two")]).0, None);
+    }
+
+    #[test]
+    fn floating_panes_resolve_to_the_tab_they_float_over() {
+        let floating = |id: u32| PaneInfo { id, is_floating: true, ..Default::default() };
+        let mut manifest = manifest();
+        manifest.panes.get_mut(&1).unwrap().push(floating(11));
+        manifest.panes.insert(5, vec![floating(12)]);
+
+        assert_eq!(resolve_in(&manifest, &[("pane_id", "11")]), (Some(1), vec![]));
+
+        // Tab 5 isn't one we know, so the pane goes to the active tab
+        let (target, warnings) = resolve_in(&manifest, &[("pane_id", "12")]);
+        assert_eq!(target, Some(0));
+        assert_eq!(warnings, vec!["⚠️  Floating pane 12 is in unknown tab 5, using active tab Some(0)".to_string()]);
+    }
+
+    #[test]
+    fn plugin_panes_sharing_an_id_are_ignored() {
+        let plugin = |id: u32, is_floating: bool| PaneInfo { id, is_plugin: true, is_floating, ..Default::default() };
+        let mut manifest = manifest();
+        manifest.panes.get_mut(&0).unwrap().push(plugin(7, false));
+        manifest.panes.get_mut(&1).unwrap().push(plugin(12, true));
+        manifest.panes.insert(5, vec![PaneInfo { id: 12, ..Default::default() }]);
+
+        // Without the index, the manifest scan skips the plugin pane 7 in tab 0
+        assert_eq!(resolve_indexed(&manifest, &HashMap::new(), &[("pane_id", "7")]), (Some(1), vec![]));
+
+        // Terminal pane 12 is tiled, so the floating plugin pane 12 doesn't send it to the active tab
+        assert_eq!(resolve_in(&manifest, &[("pane_id", "12")]), (Some(5), vec![]));
+    }
+}
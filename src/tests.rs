@@ -0,0 +1,1103 @@
+// State driven through the ZellijPlugin entry points, the way Zellij would. The tab renames
+// and switches it asks for go through the host shim, which records them here.
+
+use super::*;
+use std::cell::RefCell;
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Call {
+    Rename(usize, String),
+    Switch(usize),
+}
+
+thread_local! {
+    static CALLS: RefCell<Vec<Call>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(crate) fn record(call: Call) {
+    CALLS.with(|calls| calls.borrow_mut().push(call));
+}
+
+// Everything asked of Zellij since the last call
+fn calls() -> Vec<Call> {
+    CALLS.with(|calls| calls.borrow_mut().drain(..).collect())
+}
+
+// Just the renames, as (position, name)
+fn renames() -> Vec<(usize, String)> {
+    calls().into_iter()
+        .filter_map(|call| match call {
+            Call::Rename(position, name) => Some((position, name)),
+            Call::Switch(_) => None,
+        })
+        .collect()
+}
+
+fn plugin(config: &[(&str, &str)]) -> State {
+    let mut state = State::default();
+    state.load(config.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect());
+    calls();
+    state
+}
+
+fn tab(position: usize, name: &str) -> TabInfo {
+    TabInfo { position, name: name.to_string(), ..Default::default() }
+}
+
+// Tabs named after `names`, the first one focused
+fn tabs(names: &[&str]) -> Vec<TabInfo> {
+    names.iter().enumerate()
+        .map(|(position, name)| TabInfo { active: position == 0, ..tab(position, name) })
+        .collect()
+}
+
+// A plugin that has seen `names` as its tabs, with the first one focused
+fn plugin_with_tabs(config: &[(&str, &str)], names: &[&str]) -> State {
+    let mut state = plugin(config);
+    state.update(Event::TabUpdate(tabs(names)));
+    calls();
+    state
+}
+
+fn message(name: &str, payload: &str, args: &[(&str, &str)]) -> PipeMessage {
+    PipeMessage {
+        source: PipeSource::Cli("test".to_string()),
+        name: name.to_string(),
+        payload: Some(payload.to_string()),
+        args: args.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        is_private: false,
+    }
+}
+
+fn notify(state: &mut State, payload: &str, args: &[(&str, &str)]) {
+    state.pipe(message("notify", payload, args));
+}
+
+// Hand the recorded renames back as Zellij would, in a TabUpdate with the new names
+fn settle(state: &mut State) -> Vec<(usize, String)> {
+    let renamed = renames();
+    let mut tabs = state.all_tabs.clone();
+    for (position, name) in &renamed {
+        if let Some(tab) = tabs.iter_mut().find(|t| t.position == *position) {
+            tab.name = name.clone();
+        }
+    }
+    state.update(Event::TabUpdate(tabs));
+    renamed
+}
+
+// Focus the tab at `position`, returning the renames that caused once they've settled
+fn focus(state: &mut State, position: usize) -> Vec<(usize, String)> {
+    let tabs = state.all_tabs.iter()
+        .map(|t| TabInfo { active: t.position == position, ..t.clone() })
+        .collect();
+    state.update(Event::TabUpdate(tabs));
+    settle(state)
+}
+
+// Zellij's view of the tabs: each listed tab holds the terminal panes with the given ids
+fn panes(tabs: &[&[u32]]) -> Event {
+    let panes = tabs.iter().enumerate()
+        .map(|(position, ids)| {
+            (position, ids.iter().map(|id| PaneInfo { id: *id, ..Default::default() }).collect())
+        })
+        .collect();
+    Event::PaneUpdate(PaneManifest { panes })
+}
+
+// A key pressed in the plugin pane
+fn key(bare_key: BareKey) -> Event {
+    Event::Key(KeyWithModifier::new(bare_key))
+}
+
+// Tell the plugin which session it runs in
+fn in_session(state: &mut State, name: &str) {
+    let session = SessionInfo { name: name.to_string(), is_current_session: true, ..Default::default() };
+    state.update(Event::SessionUpdate(vec![session], vec![]));
+}
+
+fn name_of(state: &State, position: usize) -> String {
+    state.all_tabs.iter().find(|t| t.position == position).unwrap().name.clone()
+}
+
+#[test]
+fn denied_permission_leaves_tabs_and_state_alone() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    settle(&mut state);
+    assert_eq!(name_of(&state, 1), "build ✅");
+
+    state.update(Event::PermissionRequestResult(PermissionStatus::Denied));
+    assert!(focus(&mut state, 1).is_empty());
+    assert!(state.decorated_tabs.contains(&state.tab_key(1, None)));
+
+    state.update(Event::PermissionRequestResult(PermissionStatus::Granted));
+    state.pipe(message("notify-clear", "", &[("tab_position", "1")]));
+    assert_eq!(renames(), vec![(1, "build".to_string())]);
+}
+
+#[test]
+fn sessions_sharing_a_position_are_tracked_apart() {
+    // Messages routed by session_name can arrive before the first SessionUpdate
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1"), ("session_name", "A")]);
+    notify(&mut state, "stop", &[("tab_position", "1"), ("session_name", "B")]);
+    settle(&mut state);
+    assert_eq!(state.notification_counts.get(&state.tab_key(1, Some("A"))), Some(&1));
+    assert_eq!(state.notification_counts.get(&state.tab_key(1, Some("B"))), Some(&1));
+
+    state.pipe(message("notify-clear", "", &[("tab_position", "1"), ("session_name", "A")]));
+    assert!(!state.decorated_tabs.contains(&state.tab_key(1, Some("A"))));
+    assert!(state.decorated_tabs.contains(&state.tab_key(1, Some("B"))));
+}
+
+#[test]
+fn state_from_before_the_session_was_known_is_kept() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    settle(&mut state);
+
+    in_session(&mut state, "work");
+    assert_eq!(state.tab_key(1, None).session, "work");
+    assert!(state.decorated_tabs.contains(&state.tab_key(1, None)));
+    state.pipe(message("notify-clear", "", &[("tab_position", "1"), ("session_name", "work")]));
+    assert_eq!(renames(), vec![(1, "build".to_string())]);
+}
+
+#[test]
+fn state_follows_a_tab_shifted_by_a_close() {
+    let mut state = plugin_with_tabs(&[], &["editor", "logs", "build"]);
+    state.update(panes(&[&[1], &[2], &[3]]));
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    settle(&mut state);
+
+    // "logs" closes, so "build" becomes the tab at position 1
+    state.update(panes(&[&[1], &[3]]));
+    state.update(Event::TabUpdate(vec![tab(0, "editor"), tab(1, "build ✅")]));
+    assert!(state.decorated_tabs.contains(&state.tab_key(1, None)));
+    assert!(!state.decorated_tabs.contains(&state.tab_key(2, None)));
+
+    state.pipe(message("notify-clear", "", &[("tab_position", "1")]));
+    assert_eq!(renames(), vec![(1, "build".to_string())]);
+}
+
+#[test]
+fn state_of_a_closed_tab_is_dropped() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build", "logs"]);
+    state.update(panes(&[&[1], &[2], &[3]]));
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    settle(&mut state);
+
+    // "build" closes and "logs" takes its position, undecorated
+    state.update(panes(&[&[1], &[3]]));
+    state.update(Event::TabUpdate(vec![tab(0, "editor"), tab(1, "logs")]));
+    assert!(state.decorated_tabs.is_empty());
+    assert!(state.notification_counts.is_empty());
+
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(state.notification_counts.get(&state.tab_key(1, None)), Some(&1));
+}
+
+#[test]
+fn custom_preset_emoji_is_stripped_on_focus() {
+    let mut state = plugin_with_tabs(&[("presets", r#"{"deploy": {"emoji": "🚢"}}"#)], &["editor", "build"]);
+    notify(&mut state, "deploy", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build 🚢".to_string())]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+}
+
+#[test]
+fn prefix_emoji_is_cleaned_on_refocus() {
+    let mut state = plugin_with_tabs(&[("position", "prefix")], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "✅ build".to_string())]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+}
+
+#[test]
+fn repeat_notifications_count_up_until_focus() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅".to_string())]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅×2".to_string())]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅×3".to_string())]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    focus(&mut state, 0);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅".to_string())]);
+}
+
+#[test]
+fn focus_clean_can_be_turned_off() {
+    let mut state = plugin_with_tabs(&[("clear_on_focus", "false")], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    settle(&mut state);
+
+    assert!(focus(&mut state, 1).is_empty());
+    assert_eq!(name_of(&state, 1), "build ✅");
+    // Focus is still tracked for everything else that relies on it
+    assert_eq!(state.focused_tab_position, Some(1));
+}
+
+#[test]
+fn labeled_and_unlabeled_presets() {
+    let presets = r#"{"done": {"emoji": "✅", "label": "done"}, "ping": {"emoji": "🔔"}}"#;
+    let mut state = plugin_with_tabs(&[("presets", presets)], &["editor", "build", "logs"]);
+    notify(&mut state, "done", &[("tab_position", "1")]);
+    notify(&mut state, "ping", &[("tab_position", "2")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅ done".to_string()), (2, "logs 🔔".to_string())]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    assert_eq!(focus(&mut state, 2), vec![(2, "logs".to_string())]);
+}
+
+#[test]
+fn custom_separator_renames_and_strips() {
+    let mut state = plugin_with_tabs(&[("separator", " | ")], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build | ✅".to_string())]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build | ✅×2".to_string())]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+}
+
+#[test]
+fn tab_name_matches_a_tab_that_carries_an_emoji() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_name", "build")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅".to_string())]);
+
+    notify(&mut state, "notification", &[("tab_name", "build")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ⚡×2".to_string())]);
+    notify(&mut state, "stop", &[("tab_name", "build ⚡")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅×3".to_string())]);
+
+    notify(&mut state, "stop", &[("tab_name", "missing")]);
+    assert!(renames().is_empty());
+}
+
+#[test]
+fn messages_for_another_session_are_ignored() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    in_session(&mut state, "work");
+    assert!(!state.session_in_scope(Some("play")));
+    assert!(state.session_in_scope(Some("work")));
+    assert!(state.session_in_scope(None));
+
+    notify(&mut state, "stop", &[("tab_position", "1"), ("session_name", "play")]);
+    assert!(renames().is_empty());
+    notify(&mut state, "stop", &[("tab_position", "1"), ("session_name", "work")]);
+    assert_eq!(renames(), vec![(1, "build ✅".to_string())]);
+}
+
+#[test]
+fn empty_and_oversized_preset_emojis_are_skipped() {
+    let presets = r#"{"blank": {"emoji": " "}, "long": {"emoji": "🔴🟠🟡🟢🔵🟣"}, "ok": {"emoji": "🟢"}}"#;
+    let mut state = plugin_with_tabs(&[("presets", presets)], &["editor", "build"]);
+    assert_eq!(state.presets.keys().collect::<Vec<_>>(), ["ok"]);
+
+    // A skipped preset is an unknown one
+    notify(&mut state, "long", &[("tab_position", "1")]);
+    assert_eq!(renames(), vec![(1, "build ❓".to_string())]);
+}
+
+#[test]
+fn empty_payload_and_unknown_keys_use_the_fallbacks() {
+    let config = [("default_emoji", "ℹ️"), ("unknown_emoji", "🤷")];
+    let mut state = plugin_with_tabs(&config, &["editor", "build", "logs"]);
+    notify(&mut state, "", &[("tab_position", "1")]);
+    notify(&mut state, "no-such-preset", &[("tab_position", "2")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ℹ️".to_string()), (2, "logs 🤷".to_string())]);
+
+    // Both are managed, so they strip like preset emojis
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    assert_eq!(focus(&mut state, 2), vec![(2, "logs".to_string())]);
+}
+
+#[test]
+fn multi_emoji_preset_strips_without_residue() {
+    let mut state = plugin_with_tabs(&[("presets", r#"{"alarm": {"emoji": "🚨🔥"}}"#)], &["editor", "🔥 hot"]);
+    notify(&mut state, "alarm", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "🔥 hot 🚨🔥".to_string())]);
+    notify(&mut state, "alarm", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "🔥 hot 🚨🔥×2".to_string())]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "🔥 hot".to_string())]);
+}
+
+#[test]
+fn broadcast_renames_every_tab() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build", "logs"]);
+    notify(&mut state, "stop", &[("target", "all")]);
+    assert_eq!(settle(&mut state), vec![
+        (0, "editor ✅".to_string()),
+        (1, "build ✅".to_string()),
+        (2, "logs ✅".to_string()),
+    ]);
+
+    state.pipe(message("notify-all", "notification", &[("skip_active", "true")]));
+    assert_eq!(renames(), vec![(1, "build ⚡×2".to_string()), (2, "logs ⚡×2".to_string())]);
+}
+
+#[test]
+fn lower_priority_never_replaces_higher() {
+    let presets = r#"{"info": {"emoji": "ℹ️", "priority": 1}, "error": {"emoji": "❌", "priority": 5}}"#;
+    let mut state = plugin_with_tabs(&[("presets", presets)], &["editor", "build"]);
+    notify(&mut state, "info", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ℹ️".to_string())]);
+    notify(&mut state, "error", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ❌×2".to_string())]);
+
+    notify(&mut state, "info", &[("tab_position", "1")]);
+    assert!(settle(&mut state).is_empty());
+    assert_eq!(name_of(&state, 1), "build ❌×2");
+}
+
+#[test]
+fn notifications_inside_the_debounce_window_rename_once() {
+    let mut state = plugin_with_tabs(&[("debounce_ms", "500")], &["editor", "build"]);
+    notify(&mut state, "notification", &[("tab_position", "1")]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert!(renames().is_empty());
+
+    // The window is still open
+    state.update(Event::Timer(0.0));
+    assert!(renames().is_empty());
+
+    // Close it
+    for (_, flush_at) in state.pending_renames.values_mut() {
+        *flush_at = 0.0;
+    }
+    state.update(Event::Timer(0.5));
+    assert_eq!(renames(), vec![(1, "build ✅×2".to_string())]);
+    assert!(state.pending_renames.is_empty());
+}
+
+#[test]
+fn only_the_configured_pipe_name_is_handled() {
+    let mut state = plugin_with_tabs(&[("pipe_name", "znotify")], &["editor", "build"]);
+    state.pipe(message("notify", "stop", &[("tab_position", "1")]));
+    state.pipe(message("other-plugin", "stop", &[("tab_position", "1")]));
+    assert!(renames().is_empty());
+    assert!(state.decorated_tabs.is_empty());
+
+    state.pipe(message("znotify", "stop", &[("tab_position", "1")]));
+    assert_eq!(settle(&mut state), vec![(1, "build ✅".to_string())]);
+    state.pipe(message("znotify-clear", "", &[("tab_position", "1")]));
+    assert_eq!(renames(), vec![(1, "build".to_string())]);
+}
+
+#[test]
+fn pane_index_maps_pane_ids_to_tabs() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build", "logs"]);
+    state.update(panes(&[&[1, 2], &[3], &[4, 5]]));
+    assert_eq!(state.pane_tabs.get("5"), Some(&2));
+    assert_eq!(state.pane_tabs.get("3"), Some(&1));
+    assert_eq!(state.pane_tabs.get("9"), None);
+
+    notify(&mut state, "stop", &[("pane_id", "5")]);
+    assert_eq!(renames(), vec![(2, "logs ✅".to_string())]);
+}
+
+#[test]
+fn a_plugin_pane_sharing_an_id_does_not_claim_the_terminal_pane() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    let plugin_pane = PaneInfo { id: 2, is_plugin: true, ..Default::default() };
+    let terminal_pane = PaneInfo { id: 2, ..Default::default() };
+    state.update(Event::PaneUpdate(PaneManifest {
+        panes: HashMap::from([(0, vec![plugin_pane]), (1, vec![terminal_pane])]),
+    }));
+    assert_eq!(state.pane_tabs.get("2"), Some(&1));
+
+    notify(&mut state, "stop", &[("pane_id", "2")]);
+    assert_eq!(renames(), vec![(1, "build ✅".to_string())]);
+}
+
+#[test]
+fn tabs_listed_out_of_position_order_keep_their_positions() {
+    let mut state = plugin(&[]);
+    state.update(Event::TabUpdate(vec![
+        tab(2, "logs"),
+        TabInfo { active: true, ..tab(0, "editor") },
+        tab(1, "build"),
+    ]));
+    assert_eq!(state.focused_tab_position, Some(0));
+
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    notify(&mut state, "notification", &[("tab_number", "2")]);
+    assert_eq!(settle(&mut state), vec![(2, "logs ✅".to_string()), (1, "build ⚡".to_string())]);
+
+    assert_eq!(focus(&mut state, 2), vec![(2, "logs".to_string())]);
+    assert_eq!(name_of(&state, 1), "build ⚡");
+}
+
+#[test]
+fn message_renders_after_the_emoji_and_strips_with_it() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1"), ("message", "tests passed")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅ tests passed".to_string())]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+}
+
+#[test]
+fn a_message_is_only_stripped_from_the_tab_it_was_put_on() {
+    let mut state = plugin_with_tabs(&[("position", "prefix")], &["editor", "build", "ci logs"]);
+    notify(&mut state, "stop", &[("tab_position", "1"), ("message", "ci")]);
+    assert_eq!(settle(&mut state), vec![(1, "✅ ci build".to_string())]);
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    assert!(state.message_labels.is_empty());
+
+    // "ci" was tab 1's message, not part of tab 2's decoration
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    assert_eq!(settle(&mut state), vec![(2, "✅ ci logs".to_string())]);
+    assert_eq!(focus(&mut state, 2), vec![(2, "ci logs".to_string())]);
+}
+
+#[test]
+fn query_reply_lists_decorated_tabs_as_json() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build", "logs"]);
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    notify(&mut state, "notification", &[("tab_position", "1")]);
+    settle(&mut state);
+
+    assert_eq!(query_reply(&state.pending_notifications()),
+        r#"[{"position":1,"name":"build","emoji":"⚡"},{"position":2,"name":"logs","emoji":"✅×2"}]"#);
+    assert_eq!(query_reply(&[]), "[]");
+}
+
+#[test]
+fn focused_and_background_tabs_get_their_own_emoji() {
+    let presets = r#"{"stop": {"emoji": "✅", "foreground_emoji": "✔️", "background_emoji": "🟢"}}"#;
+    let mut state = plugin_with_tabs(&[("presets", presets), ("clear_on_focus", "false")], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "0")]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(0, "editor ✔️".to_string()), (1, "build 🟢".to_string())]);
+}
+
+#[test]
+fn exit_status_picks_the_command_emoji() {
+    assert_eq!(command_status_emoji(Some(0), "ok", "fail"), "ok");
+    assert_eq!(command_status_emoji(Some(1), "ok", "fail"), "fail");
+    assert_eq!(command_status_emoji(Some(-1), "ok", "fail"), "fail");
+    assert_eq!(command_status_emoji(None, "ok", "fail"), "fail");
+
+    let mut state = plugin_with_tabs(&[("auto_command_status", "true")], &["editor", "build", "logs"]);
+    let command = |id, exit_status: Option<i32>| PaneInfo { id, exited: true, exit_status, ..Default::default() };
+    let running = |id| PaneInfo { id, ..Default::default() };
+    let manifest = |panes: Vec<(usize, PaneInfo)>| {
+        let mut tabs: HashMap<usize, Vec<PaneInfo>> = HashMap::new();
+        for (tab, pane) in panes {
+            tabs.entry(tab).or_default().push(pane);
+        }
+        Event::PaneUpdate(PaneManifest { panes: tabs })
+    };
+    state.update(manifest(vec![(0, running(1)), (1, running(2)), (2, running(3))]));
+    state.update(manifest(vec![(0, running(1)), (1, command(2, Some(0))), (2, command(3, Some(2)))]));
+    let mut renamed = settle(&mut state);
+    renamed.sort();
+    assert_eq!(renamed, vec![(1, "build ✅".to_string()), (2, "logs ❌".to_string())]);
+
+    // An exit is only news once
+    state.update(manifest(vec![(0, running(1)), (1, command(2, Some(0))), (2, command(3, Some(2)))]));
+    assert!(renames().is_empty());
+}
+
+#[test]
+fn focused_target_is_skipped_when_suppressed() {
+    let mut state = plugin_with_tabs(&[("suppress_foreground", "true")], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "0")]);
+    assert!(renames().is_empty());
+    assert!(state.decorated_tabs.is_empty());
+
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(renames(), vec![(1, "build ✅".to_string())]);
+}
+
+#[test]
+fn debug_flag_maps_to_a_log_level() {
+    let config = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    };
+    assert_eq!(log_level(&config(&[])), LogLevel::Warn);
+    assert_eq!(log_level(&config(&[("debug", "true")])), LogLevel::Debug);
+    assert_eq!(log_level(&config(&[("debug", "false")])), LogLevel::Warn);
+    // log_level wins over debug, and a bad one falls back to it
+    assert_eq!(log_level(&config(&[("debug", "true"), ("log_level", "Error")])), LogLevel::Error);
+    assert_eq!(log_level(&config(&[("debug", "true"), ("log_level", "loud")])), LogLevel::Debug);
+    assert_eq!(log_level(&config(&[("log_level", " off ")])), LogLevel::Off);
+}
+
+#[test]
+fn user_emoji_survives_while_ours_is_cleaned() {
+    // ✅ is managed, but the user typed this one themselves
+    let mut state = plugin_with_tabs(&[], &["editor", "✅ done list"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "✅ done list ✅".to_string())]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "✅ done list".to_string())]);
+}
+
+#[test]
+fn long_names_are_truncated_and_restored() {
+    assert_eq!(truncate("build", 5), "build");
+    assert_eq!(truncate("builds", 5), "buil…");
+    // Emoji take two columns and are never split
+    assert_eq!(truncate("🚀🚀🚀", 5), "🚀🚀…");
+    assert_eq!(truncate("🚀🚀🚀", 6), "🚀🚀🚀");
+
+    let mut state = plugin_with_tabs(&[("max_name_len", "5")], &["editor", "build", "frontend"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅".to_string()), (2, "fron… ✅".to_string())]);
+
+    assert_eq!(focus(&mut state, 2), vec![(2, "frontend".to_string())]);
+}
+
+#[test]
+fn without_presets_the_builtin_table_is_used() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build", "logs"]);
+    assert_eq!(state.presets.len(), DEFAULT_PRESETS.len());
+    notify(&mut state, "subagent-stop", &[("tab_position", "1")]);
+    assert_eq!(renames(), vec![(1, "build 🔴".to_string())]);
+
+    // An explicitly empty table has nothing to find, so every key is unknown
+    let mut state = plugin_with_tabs(&[("presets", "{}")], &["editor", "build"]);
+    assert!(state.presets.is_empty());
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(renames(), vec![(1, "build ❓".to_string())]);
+}
+
+#[test]
+fn muted_tabs_match_by_position_and_name() {
+    let patterns = vec!["2".to_string(), "scratch*".to_string()];
+    assert!(is_muted(&patterns, 2, "logs"));
+    assert!(is_muted(&patterns, 0, "scratchpad"));
+    assert!(!is_muted(&patterns, 1, "build"));
+    assert!(!is_muted(&patterns, 1, "my scratch"));
+
+    let mut state = plugin_with_tabs(&[("mute_tabs", "2, scratch*")], &["editor", "scratchpad", "logs", "build"]);
+    notify(&mut state, "stop", &[("target", "all")]);
+    assert_eq!(renames(), vec![(0, "editor ✅".to_string()), (3, "build ✅".to_string())]);
+}
+
+#[test]
+fn pane_trigger_cleans_the_focused_tab_on_pane_update() {
+    let mut state = plugin_with_tabs(&[("clean_triggers", "pane")], &["editor", "build", "logs"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    settle(&mut state);
+
+    // Focus alone no longer cleans
+    assert!(focus(&mut state, 1).is_empty());
+
+    state.update(panes(&[&[1], &[2], &[3]]));
+    assert_eq!(settle(&mut state), vec![(1, "build".to_string())]);
+    assert_eq!(name_of(&state, 2), "logs ✅");
+}
+
+#[test]
+fn clear_key_strips_every_tab() {
+    let mut state = plugin_with_tabs(&[("clear_key", "x")], &["editor", "build", "logs"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    notify(&mut state, "error", &[("tab_position", "2")]);
+    settle(&mut state);
+
+    assert!(!state.update(key(BareKey::Char('c'))));
+    assert!(!state.update(Event::Key(KeyWithModifier::new(BareKey::Char('x')).with_ctrl_modifier())));
+    assert!(renames().is_empty());
+
+    assert!(state.update(key(BareKey::Char('x'))));
+    let mut cleared = settle(&mut state);
+    cleared.sort();
+    assert_eq!(cleared, vec![(1, "build".to_string()), (2, "logs".to_string())]);
+}
+
+#[test]
+fn inline_presets_win_over_presets_file() {
+    let path = std::env::temp_dir().join(format!("znotify-presets-{}.json", std::process::id()));
+    std::fs::write(&path, r#"{"stop": {"emoji": "🟢"}, "deploy": {"emoji": "🚀"}}"#).unwrap();
+    let config = [("presets_file", path.to_str().unwrap()), ("presets", r#"{"stop": {"emoji": "🏁"}}"#)];
+    let state = plugin(&config);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(state.presets["stop"].emoji, "🏁");
+    assert_eq!(state.presets["deploy"].emoji, "🚀");
+    // Neither source mentions it, and without a theme set the built-ins don't fill in
+    assert!(!state.presets.contains_key("notification"));
+
+    // A missing file leaves the inline presets
+    let state = plugin(&config);
+    assert_eq!(state.presets.len(), 1);
+    assert_eq!(state.presets["stop"].emoji, "🏁");
+}
+
+#[test]
+fn preset_color_puts_its_circle_before_the_emoji() {
+    let presets = r#"{"error": {"emoji": "❌", "color": "Red"}, "odd": {"emoji": "🦄", "color": "pink"}}"#;
+    let mut state = plugin_with_tabs(&[("presets", presets)], &["editor", "build", "logs"]);
+    assert_eq!(state.presets["error"].emoji, "🔴❌");
+    assert_eq!(state.presets["odd"].emoji, "🦄");
+
+    notify(&mut state, "error", &[("tab_position", "1")]);
+    notify(&mut state, "odd", &[("tab_position", "2")]);
+    assert_eq!(settle(&mut state), vec![(1, "build 🔴❌".to_string()), (2, "logs 🦄".to_string())]);
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+
+    // The text theme has no colored circles
+    let state = plugin(&[("presets", presets), ("no_emoji", "true")]);
+    assert_eq!(state.presets["error"].emoji, "❌");
+}
+
+#[test]
+fn renotifying_to_the_same_name_renames_nothing() {
+    let mut state = plugin_with_tabs(&[("max_count", "1")], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅".to_string())]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✅×1+".to_string())]);
+
+    // Past the cap the badge no longer changes
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert!(calls().is_empty());
+    assert_eq!(name_of(&state, 1), "build ✅×1+");
+}
+
+#[test]
+fn ascii_theme_renders_and_strips_its_symbols() {
+    let mut state = plugin_with_tabs(&[("theme", "ascii")], &["editor", "build", "logs"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    notify(&mut state, "no-such-preset", &[("tab_position", "2")]);
+    assert_eq!(settle(&mut state), vec![(1, "build ✓".to_string()), (2, "logs ?".to_string())]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    assert_eq!(focus(&mut state, 2), vec![(2, "logs".to_string())]);
+
+    // A preset overrides just its own entry
+    let state = plugin(&[("theme", "ascii"), ("presets", r#"{"stop": {"emoji": "🏁"}}"#)]);
+    assert_eq!(state.presets["stop"].emoji, "🏁");
+    assert_eq!(state.presets["subagent-stop"].emoji, "✗");
+}
+
+#[test]
+fn notifications_wait_for_the_rename_permission() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    state.update(Event::PermissionRequestResult(PermissionStatus::Denied));
+    assert!(!state.can_rename);
+
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert!(calls().is_empty());
+    assert!(!state.decorated_tabs.contains(&state.tab_key(1, None)));
+    assert!(!state.notification_counts.contains_key(&state.tab_key(1, None)));
+
+    state.update(Event::PermissionRequestResult(PermissionStatus::Granted));
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(renames(), vec![(1, "build ✅".to_string())]);
+}
+
+#[test]
+fn clear_with_an_emoji_strips_only_that_one() {
+    let presets = r#"{"info": {"emoji": "ℹ️"}, "error": {"emoji": "❌"}}"#;
+    let mut state = plugin_with_tabs(&[("presets", presets)], &["editor", "api"]);
+    // Named by hand, so the ℹ️ isn't a decoration of ours until the tab is notified
+    state.update(Event::TabUpdate(tabs(&["editor", "api ℹ️"])));
+    notify(&mut state, "error", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "api ℹ️ ❌".to_string())]);
+
+    let clear = |state: &mut State, emoji: &str| {
+        state.pipe(message("notify-clear", "", &[("tab_position", "1"), ("emoji", emoji)]));
+        settle(state)
+    };
+    // Not on the tab, so nothing to do
+    assert!(clear(&mut state, "🚀").is_empty());
+    assert_eq!(clear(&mut state, "ℹ️"), vec![(1, "api ❌".to_string())]);
+    assert!(state.decorated_tabs.contains(&state.tab_key(1, None)));
+
+    // Stripping the last one is a full clear
+    assert_eq!(clear(&mut state, "❌"), vec![(1, "api".to_string())]);
+    assert!(!state.decorated_tabs.contains(&state.tab_key(1, None)));
+}
+
+#[test]
+fn tab_positions_parse_in_order_skipping_bad_entries() {
+    assert_eq!(parse_positions("1, 3,x,3,", 0), (vec![1, 3], vec!["x"]));
+    assert_eq!(parse_positions("0,2,-1", 1), (vec![1], vec!["0", "-1"]));
+    assert_eq!(parse_positions("", 0), (vec![], vec![]));
+
+    let mut state = plugin_with_tabs(&[], &["editor", "build", "logs", "docs"]);
+    notify(&mut state, "stop", &[("tab_positions", "3, 1,nope,9")]);
+    // 9 isn't a tab, so only the two that exist are renamed
+    assert_eq!(renames(), vec![(3, "docs ✅".to_string()), (1, "build ✅".to_string())]);
+}
+
+#[test]
+fn notifications_in_the_cooldown_after_a_clear_are_dropped() {
+    let mut state = plugin_with_tabs(&[("cooldown_ms", "60000")], &["editor", "build", "logs"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    settle(&mut state);
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    focus(&mut state, 0);
+
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert!(renames().is_empty());
+    assert!(!state.decorated_tabs.contains(&state.tab_key(1, None)));
+
+    // Focusing a tab with nothing on it doesn't start a cooldown
+    focus(&mut state, 2);
+    focus(&mut state, 0);
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    assert_eq!(renames(), vec![(2, "logs ✅".to_string())]);
+
+    // Once the window has passed the tab can be notified again
+    state.cleared_at.insert(state.tab_key(1, None), now_secs() - 61.0);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(renames(), vec![(1, "build ✅".to_string())]);
+}
+
+#[test]
+fn preset_timeouts_clear_tabs_at_different_times() {
+    let presets = r#"{"flash": {"emoji": "⚡", "clear_after_secs": 5}, "error": {"emoji": "🔴"}, "pinned": {"emoji": "📌", "clear_after_secs": 0}}"#;
+    let mut state = plugin_with_tabs(&[("presets", presets), ("clear_after_secs", "300")], &["editor", "build", "logs", "docs"]);
+    notify(&mut state, "flash", &[("tab_position", "1")]);
+    notify(&mut state, "error", &[("tab_position", "2")]);
+    notify(&mut state, "pinned", &[("tab_position", "3")]);
+    settle(&mut state);
+
+    let age = |state: &mut State, secs: f64| {
+        for notified in state.notified_at.values_mut() {
+            *notified -= secs;
+        }
+        state.update(Event::Timer(0.0));
+        settle(state)
+    };
+    assert!(age(&mut state, 1.0).is_empty());
+    assert_eq!(age(&mut state, 10.0), vec![(1, "build".to_string())]);
+    // The global timeout applies to the preset without its own
+    assert_eq!(age(&mut state, 300.0), vec![(2, "logs".to_string())]);
+    // and a preset's 0 turns auto-clear off for it
+    assert!(age(&mut state, 3600.0).is_empty());
+    assert_eq!(name_of(&state, 3), "docs 📌");
+}
+
+#[test]
+fn synced_tabs_are_skipped_when_configured() {
+    let synced = |state: &mut State| {
+        let mut tabs = tabs(&["editor", "build", "logs"]);
+        tabs[1].is_sync_panes_active = true;
+        state.update(Event::TabUpdate(tabs));
+        calls();
+    };
+
+    let mut state = plugin(&[("skip_sync_tabs", "true")]);
+    synced(&mut state);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert!(renames().is_empty());
+    notify(&mut state, "stop", &[("target", "all")]);
+    assert_eq!(renames(), vec![(0, "editor ✅".to_string()), (2, "logs ✅".to_string())]);
+
+    // Off by default
+    let mut state = plugin(&[]);
+    synced(&mut state);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(renames(), vec![(1, "build ✅".to_string())]);
+}
+
+#[test]
+fn no_emoji_renders_and_strips_ascii_markers() {
+    let mut state = plugin_with_tabs(&[("no_emoji", "true")], &["editor", "build", "logs"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    notify(&mut state, "subagent-stop", &[("tab_position", "2")]);
+    notify(&mut state, "no-such-preset", &[("tab_position", "2")]);
+    assert_eq!(settle(&mut state), vec![
+        (1, "build [ok]".to_string()),
+        (1, "build [ok]×2".to_string()),
+        (2, "logs [err]".to_string()),
+        (2, "logs [?]×2".to_string()),
+    ]);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    assert_eq!(focus(&mut state, 2), vec![(2, "logs".to_string())]);
+}
+
+#[test]
+fn replace_modes_over_the_same_sequence() {
+    let run = |mode: &str| {
+        let presets = r#"{"chat": {"emoji": "💬", "priority": 1}, "error": {"emoji": "🔴", "priority": 2}}"#;
+        let mut state = plugin_with_tabs(&[("presets", presets), ("replace_mode", mode)], &["editor", "build"]);
+        let mut seen = Vec::new();
+        for name in ["chat", "error", "chat"] {
+            notify(&mut state, name, &[("tab_position", "1")]);
+            seen.extend(settle(&mut state).into_iter().map(|(_, name)| name));
+        }
+        // A clear resets every mode, so the next notification shows again
+        state.pipe(message("notify-clear", "", &[("tab_position", "1")]));
+        settle(&mut state);
+        notify(&mut state, "chat", &[("tab_position", "1")]);
+        seen.extend(settle(&mut state).into_iter().map(|(_, name)| name));
+        seen
+    };
+
+    assert_eq!(run("always"), ["build 💬", "build 🔴×2", "build 💬×3", "build 💬"]);
+    assert_eq!(run("priority"), ["build 💬", "build 🔴×2", "build 💬"]);
+    assert_eq!(run("sticky"), ["build 💬", "build 💬"]);
+}
+
+#[test]
+fn picker_moves_between_the_tab_and_preset_lists() {
+    let mut picker = Picker::default();
+    // Selection stays inside the list
+    assert_eq!(picker.handle_key(&BareKey::Up, 3, 2), PickerAction::Stay);
+    assert_eq!(picker.tab, 0);
+    for _ in 0..5 {
+        picker.handle_key(&BareKey::Char('j'), 3, 2);
+    }
+    assert_eq!(picker.tab, 2);
+
+    assert_eq!(picker.handle_key(&BareKey::Enter, 3, 2), PickerAction::Stay);
+    assert!(picker.choosing_preset);
+    picker.handle_key(&BareKey::Down, 3, 2);
+    picker.handle_key(&BareKey::Down, 3, 2);
+    assert_eq!((picker.tab, picker.preset), (2, 1));
+
+    // Esc backs out to the tabs, keeping both selections
+    picker.handle_key(&BareKey::Esc, 3, 2);
+    assert!(!picker.choosing_preset);
+    picker.handle_key(&BareKey::Enter, 3, 2);
+    assert_eq!(picker.handle_key(&BareKey::Enter, 3, 2), PickerAction::Apply { tab: 2, preset: 1 });
+
+    assert_eq!(Picker::default().handle_key(&BareKey::Esc, 3, 2), PickerAction::Close);
+    // An empty list has nothing to pick
+    let mut empty = Picker::default();
+    empty.handle_key(&BareKey::Enter, 0, 2);
+    assert!(!empty.choosing_preset);
+}
+
+#[test]
+fn picker_keys_tag_the_chosen_tab() {
+    let presets = r#"{"chat": {"emoji": "💬"}, "error": {"emoji": "🔴"}}"#;
+    let mut state = plugin_with_tabs(&[("presets", presets)], &["editor", "build"]);
+    assert!(state.update(key(BareKey::Char('p'))));
+    for bare_key in [BareKey::Down, BareKey::Enter, BareKey::Down, BareKey::Enter] {
+        assert!(state.update(key(bare_key)));
+    }
+    assert_eq!(renames(), vec![(1, "build 🔴".to_string())]);
+    assert!(state.picker.is_none());
+}
+
+#[test]
+fn max_count_caps_the_badge_at_n_plus() {
+    assert_eq!(badge("⚡", 1, 0), "⚡");
+    assert_eq!(badge("⚡", 12, 0), "⚡×12");
+    assert_eq!(badge("⚡", 3, 3), "⚡×3");
+    assert_eq!(badge("⚡", 4, 3), "⚡×3+");
+
+    let mut state = plugin_with_tabs(&[("max_count", "3")], &["editor", "build"]);
+    let mut seen = Vec::new();
+    for _ in 0..5 {
+        notify(&mut state, "stop", &[("tab_position", "1")]);
+        seen.extend(settle(&mut state));
+    }
+    // The fifth already reads ×3+, so there's nothing to rename
+    assert_eq!(seen, vec![
+        (1, "build ✅".to_string()),
+        (1, "build ✅×2".to_string()),
+        (1, "build ✅×3".to_string()),
+        (1, "build ✅×3+".to_string()),
+    ]);
+    // The stored count stops one past the cap
+    assert_eq!(state.notification_counts[&state.tab_key(1, None)], 4);
+
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    focus(&mut state, 0);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    assert_eq!(renames(), vec![(1, "build ✅".to_string())]);
+}
+
+#[test]
+fn goto_switches_to_the_tab_then_clears_it() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build", "logs"]);
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    settle(&mut state);
+
+    state.pipe(message("notify-goto", "", &[("tab_name", "logs")]));
+    // switch_tab_to counts from 1, the recorded position doesn't
+    assert_eq!(calls(), vec![Call::Switch(2), Call::Rename(2, "logs".to_string())]);
+    assert!(!state.decorated_tabs.contains(&state.tab_key(2, None)));
+
+    // The focus arriving afterwards has nothing left to clean
+    state.update(Event::TabUpdate(state.all_tabs.iter().map(|t| TabInfo {
+        active: t.position == 2,
+        name: if t.position == 2 { "logs".to_string() } else { t.name.clone() },
+        ..t.clone()
+    }).collect()));
+    assert!(calls().is_empty());
+
+    // A tab that isn't there gets neither a switch nor a rename
+    state.pipe(message("notify-goto", "", &[("tab_number", "9")]));
+    state.pipe(message("notify-goto", "", &[("tab_name", "nope")]));
+    assert!(calls().is_empty());
+}
+
+#[test]
+fn a_name_that_is_only_an_emoji_gets_a_default_name() {
+    assert_eq!(non_empty_name(1, " ".to_string()), "Tab #2");
+    assert_eq!(non_empty_name(1, "build".to_string()), "build");
+
+    // A decorated tab the user renamed down to our emoji alone
+    let mut state = plugin_with_tabs(&[], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    settle(&mut state);
+    state.update(Event::TabUpdate(vec![TabInfo { active: true, ..tab(0, "editor") }, tab(1, "✅")]));
+    notify(&mut state, "stop", &[("tab_position", "1")]);
+    // Stripping our emoji leaves nothing, so the tab is decorated and cleaned back to
+    // Zellij's default name
+    assert_eq!(settle(&mut state), vec![(1, "Tab #2 ✅×2".to_string())]);
+    assert_eq!(focus(&mut state, 1), vec![(1, "Tab #2".to_string())]);
+    assert!(!state.decorated_tabs.contains(&state.tab_key(1, None)));
+}
+
+#[test]
+fn names_the_user_may_have_typed_are_not_adopted_on_load() {
+    let mut state = plugin_with_tabs(&[], &["editor", "release ✅", "✅"]);
+    assert!(state.decorated_tabs.is_empty());
+    assert!(focus(&mut state, 1).is_empty());
+    assert!(focus(&mut state, 2).is_empty());
+    assert_eq!(name_of(&state, 1), "release ✅");
+}
+
+#[test]
+fn names_only_the_plugin_renders_are_adopted_on_load() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build ✅×2", "✅×3"]);
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    assert!(focus(&mut state, 2).is_empty());
+
+    let mut state = plugin_with_tabs(&[("separator", " | ")], &["editor", "build | ✅", "release ✅"]);
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    assert!(focus(&mut state, 2).is_empty());
+}
+
+#[test]
+fn set_name_replaces_the_base_name_until_cleaned() {
+    let mut state = plugin_with_tabs(&[("max_name_len", "8")], &["editor", "zsh"]);
+    notify(&mut state, "stop", &[("tab_position", "1"), ("set_name", "deploy")]);
+    assert_eq!(settle(&mut state), vec![(1, "deploy ✅".to_string())]);
+
+    // A later notification keeps the new name
+    notify(&mut state, "subagent-stop", &[("tab_position", "1")]);
+    assert_eq!(settle(&mut state), vec![(1, "deploy 🔴×2".to_string())]);
+    assert_eq!(focus(&mut state, 1), vec![(1, "deploy".to_string())]);
+
+    // A long name set this way is shortened, and the clean gives all of it back
+    focus(&mut state, 0);
+    notify(&mut state, "stop", &[("tab_position", "1"), ("set_name", "release-candidate")]);
+    assert_eq!(settle(&mut state), vec![(1, "release… ✅".to_string())]);
+    assert_eq!(focus(&mut state, 1), vec![(1, "release-candidate".to_string())]);
+}
+
+#[test]
+fn both_tab_position_bases_reach_the_first_tab() {
+    for (base, first) in [("0", "0"), ("1", "1")] {
+        let mut state = plugin_with_tabs(&[("tab_position_base", base)], &["editor", "build"]);
+        notify(&mut state, "stop", &[("tab_position", first)]);
+        assert_eq!(renames(), vec![(0, "editor ✅".to_string())], "base {}", base);
+        notify(&mut state, "stop", &[("tab_positions", &format!("{},{}", first, first.parse::<usize>().unwrap() + 1))]);
+        assert_eq!(renames(), vec![(0, "editor ✅×2".to_string()), (1, "build ✅".to_string())], "base {}", base);
+    }
+
+    // With base 1 there's no tab 0
+    let mut state = plugin_with_tabs(&[("tab_position_base", "1")], &["editor", "build"]);
+    notify(&mut state, "stop", &[("tab_position", "0")]);
+    assert!(renames().is_empty());
+}
+
+#[test]
+fn tab_number_counts_the_tabs_shown_not_positions() {
+    let mut state = plugin(&[]);
+    state.update(Event::TabUpdate(vec![tab(5, "logs"), TabInfo { active: true, ..tab(0, "editor") }, tab(2, "build")]));
+    calls();
+
+    notify(&mut state, "stop", &[("tab_number", "2")]);
+    assert_eq!(renames(), vec![(2, "build ✅".to_string())]);
+    notify(&mut state, "stop", &[("tab_number", "3")]);
+    assert_eq!(renames(), vec![(5, "logs ✅".to_string())]);
+
+    // Past the last tab, or 0, is no tab at all
+    notify(&mut state, "stop", &[("tab_number", "4")]);
+    notify(&mut state, "stop", &[("tab_number", "0")]);
+    assert!(renames().is_empty());
+}
+
+#[test]
+fn early_pane_notifications_wait_for_the_pane_manifest() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build", "logs"]);
+    notify(&mut state, "stop", &[("pane_id", "7")]);
+    notify(&mut state, "stop", &[("pane_id", "8")]);
+    assert!(renames().is_empty());
+    assert_eq!(state.queued_messages.len(), 2);
+
+    // Pane 8 never shows up, so only pane 7's tab is renamed
+    state.update(panes(&[&[1], &[7], &[9]]));
+    assert_eq!(renames(), vec![(1, "build ✅".to_string())]);
+    assert!(state.queued_messages.is_empty());
+}
+
+#[test]
+fn queued_notifications_expire_and_are_bounded() {
+    let mut state = plugin(&[]);
+    for _ in 0..MAX_QUEUED_MESSAGES + 2 {
+        notify(&mut state, "stop", &[("pane_id", "7")]);
+    }
+    assert_eq!(state.queued_messages.len(), MAX_QUEUED_MESSAGES);
+
+    for (_, queued_at) in &mut state.queued_messages {
+        *queued_at -= QUEUED_MESSAGE_MAX_AGE_SECS + 1.0;
+    }
+    state.update(Event::TabUpdate(tabs(&["editor", "build"])));
+    state.update(panes(&[&[1], &[7]]));
+    assert!(renames().is_empty());
+    assert!(state.queued_messages.is_empty());
+}
+
+#[test]
+fn agent_emojis_override_the_preset_emoji() {
+    let agents = r#"{"reviewer": "🧐", "tester": "🧪", "broken": ""}"#;
+    let mut state = plugin_with_tabs(&[("agent_emojis", agents)], &["editor", "build", "logs", "docs"]);
+    assert!(!state.agent_emojis.contains_key("broken"));
+
+    notify(&mut state, "subagent-stop", &[("tab_position", "1"), ("agent", " reviewer ")]);
+    notify(&mut state, "subagent-stop", &[("tab_position", "2"), ("agent", "unknown")]);
+    notify(&mut state, "subagent-stop", &[("tab_position", "3"), ("agent", "tester"), ("emoji", "🚀")]);
+    assert_eq!(settle(&mut state), vec![
+        (1, "build 🧐".to_string()),
+        // No mapping for this agent, so the preset's own emoji
+        (2, "logs 🔴".to_string()),
+        // and an explicit emoji beats the agent's
+        (3, "docs 🚀".to_string()),
+    ]);
+
+    // Agent emojis are managed too, so focus strips them
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+}
+
+#[test]
+fn a_raw_emoji_is_only_stripped_from_the_tab_it_was_put_on() {
+    let mut state = plugin_with_tabs(&[], &["editor", "build", "party 🎉"]);
+    notify(&mut state, "stop", &[("tab_position", "1"), ("emoji", "🎉")]);
+    assert_eq!(settle(&mut state), vec![(1, "build 🎉".to_string())]);
+    assert_eq!(focus(&mut state, 1), vec![(1, "build".to_string())]);
+    assert!(state.raw_emojis.is_empty());
+    assert!(!state.managed_emojis.contains(&"🎉".to_string()));
+
+    // The user's own 🎉 on another tab stays when that tab is cleaned
+    notify(&mut state, "stop", &[("tab_position", "2")]);
+    assert_eq!(settle(&mut state), vec![(2, "party 🎉 ✅".to_string())]);
+    assert_eq!(focus(&mut state, 2), vec![(2, "party 🎉".to_string())]);
+}